@@ -1,81 +1,490 @@
 use nom::{
-    branch::alt, bytes::complete::tag, character::complete::u64 as nom_u64, combinator::map,
-    sequence::tuple, IResult,
+    bytes::streaming::{tag, take_while_m_n},
+    combinator::map_opt,
+    error::{Error, ErrorKind},
+    multi::separated_list1,
+    sequence::{delimited, pair},
+    IResult,
 };
-use std::env;
+use std::{env, fs::File, io::Read};
+
+// Size of each read in --stream mode, unless a test picks a smaller one.
+const CHUNK_SIZE: usize = 64 * 1024;
+// Longest partial token carried between chunks. Puzzle instructions are far
+// shorter; this only bounds variadic instructions with endless operands.
+const MAX_CARRY: usize = 1024;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 3 {
-        eprintln!("Usage: {} <part1|part2> <file_path>", args[0]);
+        eprintln!(
+            "Usage: {} <part1|part2> <file_path> [--list|--json|--stream] [--extended]",
+            args[0]
+        );
         std::process::exit(1);
     }
 
     let command = &args[1];
     let file_path = &args[2];
+    let mut output = Output::Sum;
+    let mut instructions = InstructionSet::puzzle();
+    for flag in &args[3..] {
+        match flag.as_str() {
+            "--list" => output = Output::List,
+            "--json" => output = Output::Json,
+            "--stream" => output = Output::Stream,
+            "--extended" => instructions = InstructionSet::extended(),
+            _ => {
+                eprintln!(
+                    "Invalid option: {}. Use '--list', '--json', '--stream' or '--extended'.",
+                    flag
+                );
+                std::process::exit(1);
+            }
+        }
+    }
 
     let start = std::time::Instant::now();
-    match command.as_str() {
-        "part1" => part1(file_path),
-        "part2" => part2(file_path),
+    let result = match command.as_str() {
+        "part1" => part1(file_path, &instructions, output),
+        "part2" => part2(file_path, &instructions, output),
         _ => {
             eprintln!("Invalid command: {}. Use 'part1' or 'part2'.", command);
             std::process::exit(1);
         }
+    };
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        std::process::exit(1);
     }
     let duration = start.elapsed();
     eprintln!("Time: {:.6} seconds", duration.as_secs_f64());
 }
 
+#[derive(Clone, Copy)]
+enum Output {
+    Sum,
+    List,
+    Json,
+    Stream,
+}
+
+// What a recognized instruction does once executed.
+#[derive(Debug, Clone, Copy)]
 enum ParseResult {
     Do,
     Dont,
-    Mul(u64, u64),
+    // Saves the current enabled state until the matching PopScope.
+    PushScope,
+    PopScope,
+    // An arithmetic result, already checked for overflow.
+    Value(u64),
 }
 
-fn parse_next(s: &str) -> IResult<&str, ParseResult> {
-    alt((
-        map(tag("do()"), |_| ParseResult::Do),
-        map(tag("don't()"), |_| ParseResult::Dont),
-        map(
-            tuple((tag("mul("), nom_u64, tag(","), nom_u64, tag(")"))),
-            |(_, a, _, b, _)| ParseResult::Mul(a, b),
-        ),
-    ))(s)
+// One kind of instruction the interpreter understands. New kinds only need to
+// implement this and be added to an InstructionSet; the scanners never change.
+trait InstructionKind {
+    fn name(&self) -> &'static str;
+
+    // Must be built from nom's streaming combinators so a token cut off at the
+    // end of the input reports Incomplete rather than failing.
+    fn parse<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], ParseResult>;
 }
 
-fn part1(file_path: &str) {
-    let input = std::fs::read_to_string(file_path).expect("Failed to read file");
-    let sum = parse(input, false);
-    println!("{}", sum);
+// An instruction with no operands, such as do() or don't().
+struct Keyword {
+    name: &'static str,
+    text: &'static str,
+    result: ParseResult,
 }
 
-fn parse(input: String, do_enabled: bool) -> u64 {
-    let mut input = input.as_str();
-    let mut sum = 0;
-    let mut enabled = true;
+impl InstructionKind for Keyword {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn parse<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], ParseResult> {
+        let (rem, _) = tag(self.text)(input)?;
+        Ok((rem, self.result))
+    }
+}
+
+// An instruction like mul(2,4) that folds its operands into a value. Operands
+// longer than max_digits, operand counts outside min_args..=max_args and
+// results that overflow are all treated as corruption.
+struct Arithmetic {
+    name: &'static str,
+    min_args: usize,
+    max_args: usize,
+    max_digits: usize,
+    op: fn(u64, u64) -> Option<u64>,
+}
+
+impl InstructionKind for Arithmetic {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn parse<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], ParseResult> {
+        let operand = map_opt(
+            take_while_m_n(1, self.max_digits, |c: u8| c.is_ascii_digit()),
+            |digits: &[u8]| {
+                digits.iter().try_fold(0u64, |value, digit| {
+                    value.checked_mul(10)?.checked_add((digit - b'0') as u64)
+                })
+            },
+        );
+        let (rem, operands) = delimited(
+            pair(tag(self.name), tag("(")),
+            separated_list1(tag(","), operand),
+            tag(")"),
+        )(input)?;
+
+        let value = if (self.min_args..=self.max_args).contains(&operands.len()) {
+            operands[1..]
+                .iter()
+                .try_fold(operands[0], |acc, &operand| (self.op)(acc, operand))
+        } else {
+            None
+        };
+        match value {
+            Some(value) => Ok((rem, ParseResult::Value(value))),
+            None => Err(nom::Err::Error(Error::new(input, ErrorKind::Verify))),
+        }
+    }
+}
+
+// The instructions recognized while scanning, tried in order.
+struct InstructionSet {
+    kinds: Vec<Box<dyn InstructionKind>>,
+}
+
+impl InstructionSet {
+    // Exactly the puzzle's rules: do(), don't() and mul of two 1-3 digit numbers.
+    fn puzzle() -> Self {
+        InstructionSet {
+            kinds: vec![
+                Box::new(Keyword {
+                    name: "do",
+                    text: "do()",
+                    result: ParseResult::Do,
+                }),
+                Box::new(Keyword {
+                    name: "dont",
+                    text: "don't()",
+                    result: ParseResult::Dont,
+                }),
+                Box::new(Arithmetic {
+                    name: "mul",
+                    min_args: 2,
+                    max_args: 2,
+                    max_digits: 3,
+                    op: u64::checked_mul,
+                }),
+            ],
+        }
+    }
+
+    // The puzzle's rules plus add/sub, variadic mul and push()/pop() scopes
+    // that restore the enabled state saved at the matching push().
+    fn extended() -> Self {
+        let mut set = Self::puzzle();
+        set.kinds.retain(|kind| kind.name() != "mul");
+        for (name, op) in [
+            ("mul", u64::checked_mul as fn(u64, u64) -> Option<u64>),
+            ("add", u64::checked_add),
+            ("sub", u64::checked_sub),
+        ] {
+            set.kinds.push(Box::new(Arithmetic {
+                name,
+                min_args: 2,
+                max_args: usize::MAX,
+                max_digits: 3,
+                op,
+            }));
+        }
+        set.kinds.push(Box::new(Keyword {
+            name: "push",
+            text: "push()",
+            result: ParseResult::PushScope,
+        }));
+        set.kinds.push(Box::new(Keyword {
+            name: "pop",
+            text: "pop()",
+            result: ParseResult::PopScope,
+        }));
+        set
+    }
+
+    // Returns the first kind that parses. If none does but one ran out of
+    // input, reports Incomplete so a streaming caller can wait for more bytes.
+    fn parse_next<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], (&'static str, ParseResult)> {
+        let mut incomplete = None;
+        for kind in &self.kinds {
+            match kind.parse(input) {
+                Ok((rem, parsed)) => return Ok((rem, (kind.name(), parsed))),
+                Err(nom::Err::Incomplete(needed)) => {
+                    incomplete.get_or_insert(needed);
+                }
+                Err(_) => {}
+            }
+        }
+        match incomplete {
+            Some(needed) => Err(nom::Err::Incomplete(needed)),
+            None => Err(nom::Err::Error(Error::new(input, ErrorKind::Alt))),
+        }
+    }
+}
+
+fn part1(file_path: &str, instructions: &InstructionSet, output: Output) -> Result<(), String> {
+    report(file_path, instructions, false, output)
+}
+
+// A recognized instruction along with where it was found and what it did.
+struct Instruction<'a> {
+    offset: usize,
+    raw: &'a [u8],
+    kind: &'static str,
+    enabled: bool,
+    contribution: u64,
+}
+
+// Tracks do()/don't() and open scopes so they can be carried across chunks in
+// --stream mode.
+struct State {
+    do_enabled: bool,
+    enabled: bool,
+    scopes: Vec<bool>,
+}
+
+impl State {
+    fn new(do_enabled: bool) -> Self {
+        State {
+            do_enabled,
+            enabled: true,
+            scopes: Vec::new(),
+        }
+    }
 
-    while !input.is_empty() {
-        let Ok((rem, parsed)) = parse_next(input) else {
-            input = &input[1..];
-            continue;
+    // Applies an instruction, returning whether values are enabled afterwards
+    // and what it adds to the sum.
+    fn apply(&mut self, op: ParseResult) -> (bool, u64) {
+        let contribution = match op {
+            ParseResult::Do => {
+                self.enabled = true;
+                0
+            }
+            ParseResult::Dont => {
+                self.enabled = false;
+                0
+            }
+            ParseResult::PushScope => {
+                self.scopes.push(self.enabled);
+                0
+            }
+            ParseResult::PopScope => {
+                if let Some(enabled) = self.scopes.pop() {
+                    self.enabled = enabled;
+                }
+                0
+            }
+            ParseResult::Value(value) if !self.do_enabled || self.enabled => value,
+            ParseResult::Value(_) => 0,
         };
-        input = rem;
-        match parsed {
-            ParseResult::Do => enabled = true,
-            ParseResult::Dont => enabled = false,
-            ParseResult::Mul(a, b) => {
-                if !do_enabled || enabled {
-                    sum += a * b;
+        (!self.do_enabled || self.enabled, contribution)
+    }
+}
+
+fn add_to_sum(sum: u64, contribution: u64) -> Result<u64, String> {
+    sum.checked_add(contribution)
+        .ok_or_else(|| "Sum overflowed u64".to_string())
+}
+
+// Walks corrupted memory yielding every recognized instruction in order.
+// Scans raw bytes rather than a str so corrupted memory doesn't need to be valid
+// UTF-8, and skipping a byte on a failed parse can never split a character.
+struct Scanner<'a> {
+    instructions: &'a InstructionSet,
+    input: &'a [u8],
+    offset: usize,
+    state: State,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(instructions: &'a InstructionSet, input: &'a [u8], do_enabled: bool) -> Self {
+        Scanner {
+            instructions,
+            input,
+            offset: 0,
+            state: State::new(do_enabled),
+        }
+    }
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Instruction<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.input.is_empty() {
+            let Ok((rem, (kind, parsed))) = self.instructions.parse_next(self.input) else {
+                self.input = &self.input[1..];
+                self.offset += 1;
+                continue;
+            };
+            let len = self.input.len() - rem.len();
+            let raw = &self.input[..len];
+            let offset = self.offset;
+            self.input = rem;
+            self.offset += len;
+
+            let (enabled, contribution) = self.state.apply(parsed);
+            return Some(Instruction {
+                offset,
+                raw,
+                kind,
+                enabled,
+                contribution,
+            });
+        }
+        None
+    }
+}
+
+fn parse(instructions: &InstructionSet, input: &[u8], do_enabled: bool) -> Result<u64, String> {
+    Scanner::new(instructions, input, do_enabled).try_fold(0, |sum, instruction| {
+        add_to_sum(sum, instruction.contribution)
+    })
+}
+
+// Sums a reader in fixed-size chunks so memory stays constant however large
+// the input is. A token split across a chunk boundary parses as Incomplete and
+// is carried over to the front of the buffer for the next read.
+fn parse_stream<R: Read>(
+    instructions: &InstructionSet,
+    mut reader: R,
+    do_enabled: bool,
+    chunk_size: usize,
+) -> Result<u64, String> {
+    let mut buf = vec![0u8; chunk_size + MAX_CARRY];
+    let mut carry = 0;
+    let mut state = State::new(do_enabled);
+    let mut sum = 0;
+
+    loop {
+        let read = reader
+            .read(&mut buf[carry..carry + chunk_size])
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        let eof = read == 0;
+        let filled = carry + read;
+
+        let mut input = &buf[..filled];
+        while !input.is_empty() {
+            match instructions.parse_next(input) {
+                Ok((rem, (_, parsed))) => {
+                    sum = add_to_sum(sum, state.apply(parsed).1)?;
+                    input = rem;
                 }
+                Err(nom::Err::Incomplete(_)) if !eof && input.len() <= MAX_CARRY => break,
+                Err(_) => input = &input[1..],
+            }
+        }
+        if eof {
+            return Ok(sum);
+        }
+
+        carry = input.len();
+        buf.copy_within(filled - carry..filled, 0);
+    }
+}
+
+fn report(
+    file_path: &str,
+    instructions: &InstructionSet,
+    do_enabled: bool,
+    output: Output,
+) -> Result<(), String> {
+    if let Output::Stream = output {
+        let file = File::open(file_path).expect("Failed to open file");
+        let sum = parse_stream(instructions, file, do_enabled, CHUNK_SIZE)?;
+        println!("{}", sum);
+        return Ok(());
+    }
+
+    let input = std::fs::read(file_path).expect("Failed to read file");
+    let input = input.as_slice();
+    match output {
+        Output::Sum | Output::Stream => println!("{}", parse(instructions, input, do_enabled)?),
+        Output::List => {
+            let mut sum = 0;
+            println!(
+                "{:>10}  {:<8}  {:>10}  instruction",
+                "offset", "state", "value"
+            );
+            for instruction in Scanner::new(instructions, input, do_enabled) {
+                sum = add_to_sum(sum, instruction.contribution)?;
+                println!(
+                    "{:>10}  {:<8}  {:>10}  {}",
+                    instruction.offset,
+                    if instruction.enabled {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    },
+                    instruction.contribution,
+                    String::from_utf8_lossy(instruction.raw)
+                );
             }
+            println!("{}", sum);
+        }
+        Output::Json => {
+            let mut sum = 0;
+            let rows: Vec<String> = Scanner::new(instructions, input, do_enabled)
+                .map(|instruction| {
+                    sum = add_to_sum(sum, instruction.contribution)?;
+                    // Recognized instructions are always plain ASCII with no quotes
+                    // or backslashes, so the raw text needs no escaping.
+                    Ok(format!(
+                        "{{\"offset\":{},\"kind\":\"{}\",\"raw\":\"{}\",\"enabled\":{},\"contribution\":{}}}",
+                        instruction.offset,
+                        instruction.kind,
+                        String::from_utf8_lossy(instruction.raw),
+                        instruction.enabled,
+                        instruction.contribution
+                    ))
+                })
+                .collect::<Result<_, String>>()?;
+            println!("{{\"instructions\":[{}],\"sum\":{}}}", rows.join(","), sum);
         }
     }
-    return sum;
+    Ok(())
 }
 
-fn part2(file_path: &str) {
-    let input = std::fs::read_to_string(file_path).expect("Failed to read file");
-    let sum = parse(input, true);
-    println!("{}", sum);
+fn part2(file_path: &str, instructions: &InstructionSet, output: Output) -> Result<(), String> {
+    report(file_path, instructions, true, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_chunks() {
+        // Every chunk size splits some instruction across a boundary, and the
+        // carried-over part must parse the same as reading it whole
+        let input = include_str!("../input-test2.txt");
+        let instructions = InstructionSet::puzzle();
+        assert_eq!(parse(&instructions, input.as_bytes(), true), Ok(48));
+        for chunk_size in 1..=16 {
+            let sum = parse_stream(&instructions, input.as_bytes(), true, chunk_size);
+            assert_eq!(sum, Ok(48), "chunk size {}", chunk_size);
+        }
+
+        // The first chunk ends with "mul(12", so the operand continues into
+        // the next one
+        let input = "xmul(12,3)mul(4,5)";
+        assert_eq!(
+            parse_stream(&instructions, input.as_bytes(), false, 7),
+            Ok(56)
+        );
+    }
 }
@@ -1,21 +1,102 @@
-use std::env;
+use aho_corasick::AhoCorasick;
+use std::{env, fmt};
+
+const DIRECTIONS: [(i32, i32, &str); 8] = [
+    (0, 1, "right"),
+    (1, 0, "down"),
+    (1, 1, "down-right"),
+    (-1, 1, "up-right"),
+    (0, -1, "left"),
+    (-1, 0, "up"),
+    (-1, -1, "up-left"),
+    (1, -1, "down-left"),
+];
+
+// Every rotation and reflection of a pattern. Flipped variants mirror the
+// pattern left-right before rotating it clockwise.
+const TRANSFORMS: [&str; 8] = [
+    "identity",
+    "rot90",
+    "rot180",
+    "rot270",
+    "flip",
+    "flip-rot90",
+    "flip-rot180",
+    "flip-rot270",
+];
+
+// Two MAS in the shape of an X, in any orientation.
+const XMAS_PATTERN: &str = "M.S\n.A.\nM.S";
+
+#[derive(Debug)]
+enum GridError {
+    Empty,
+    Ragged {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GridError::Empty => write!(f, "grid is empty"),
+            GridError::Ragged {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {} has {} columns, expected {}",
+                line, found, expected
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Render {
+    Ansi,
+    Html,
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        eprintln!("Usage: {} <part1|part2> <file_path>", args[0]);
+    if args.len() < 3 || (matches!(args[1].as_str(), "words" | "pattern") && args.len() < 4) {
+        eprintln!(
+            "Usage: {0} <part1|part2> <file_path> [--ansi|--html]\n       {0} words <file_path> <dictionary_path>\n       {0} pattern <file_path> <pattern_path> [transform,...]",
+            args[0]
+        );
         std::process::exit(1);
     }
 
     let command = &args[1];
     let file_path = &args[2];
+    let render = match command.as_str() {
+        "part1" | "part2" => match args.get(3).map(String::as_str) {
+            None => None,
+            Some("--ansi") => Some(Render::Ansi),
+            Some("--html") => Some(Render::Html),
+            Some(flag) => {
+                eprintln!("Invalid option: {}. Use '--ansi' or '--html'.", flag);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
 
     let start = std::time::Instant::now();
     match command.as_str() {
-        "part1" => part1(file_path),
-        "part2" => part2(file_path),
+        "part1" => part1(file_path, render),
+        "part2" => part2(file_path, render),
+        "words" => words(file_path, &args[3]),
+        "pattern" => pattern(file_path, &args[3], args.get(4).map(String::as_str)),
         _ => {
-            eprintln!("Invalid command: {}. Use 'part1' or 'part2'.", command);
+            eprintln!(
+                "Invalid command: {}. Use 'part1', 'part2', 'words' or 'pattern'.",
+                command
+            );
             std::process::exit(1);
         }
     }
@@ -23,93 +104,401 @@ fn main() {
     eprintln!("Time: {:.6} seconds", duration.as_secs_f64());
 }
 
-fn part1(file_path: &str) {
+fn load_grid(file_path: &str) -> Vec<Vec<char>> {
     let input = std::fs::read_to_string(file_path).expect("Failed to read file");
-    let grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-    let sum = count_xmas(&grid);
-    println!("{}", sum);
-}
-
-fn count_xmas(grid: &Vec<Vec<char>>) -> usize {
-    let rows = grid.len();
-    let cols = grid[0].len();
-    let directions = [
-        (0, 1),   // right
-        (1, 0),   // down
-        (1, 1),   // diagonal down-right
-        (-1, 1),  // diagonal up-right
-        (0, -1),  // left
-        (-1, 0),  // up
-        (-1, -1), // diagonal up-left
-        (1, -1),  // diagonal down-left
-    ];
-
-    let mut count = 0;
-    for row in 0..rows {
-        for col in 0..cols {
-            for &(dx, dy) in &directions {
+    parse_grid(&input).unwrap_or_else(|err| {
+        eprintln!("Invalid grid in {}: {}", file_path, err);
+        std::process::exit(1);
+    })
+}
+
+// Rejects empty and ragged input up front. Trailing blank lines are ignored.
+fn parse_grid(input: &str) -> Result<Vec<Vec<char>>, GridError> {
+    let mut grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+    while grid.last().is_some_and(Vec::is_empty) {
+        grid.pop();
+    }
+
+    let expected = grid.first().ok_or(GridError::Empty)?.len();
+    for (i, line) in grid.iter().enumerate() {
+        if line.len() != expected {
+            return Err(GridError::Ragged {
+                line: i + 1,
+                expected,
+                found: line.len(),
+            });
+        }
+    }
+    Ok(grid)
+}
+
+fn part1(file_path: &str, render: Option<Render>) {
+    let grid = load_grid(file_path);
+    let Some(render) = render else {
+        let sum = count_xmas(&grid);
+        println!("{}", sum);
+        return;
+    };
+
+    let mut highlighted = grid
+        .iter()
+        .map(|line| vec![false; line.len()])
+        .collect::<Vec<_>>();
+    for (row, col, dx, dy) in find_xmas(&grid) {
+        for i in 0.."XMAS".len() as i32 {
+            highlighted[(row as i32 + dx * i) as usize][(col as i32 + dy * i) as usize] = true;
+        }
+    }
+    print!("{}", render_grid(&grid, &highlighted, render));
+}
+
+fn count_xmas(grid: &[Vec<char>]) -> usize {
+    find_xmas(grid).len()
+}
+
+// Returns the start cell and direction of every XMAS in the grid.
+fn find_xmas(grid: &[Vec<char>]) -> Vec<(usize, usize, i32, i32)> {
+    let mut found = Vec::new();
+    for row in 0..grid.len() {
+        for col in 0..grid[row].len() {
+            for &(dx, dy, _) in &DIRECTIONS {
                 if check_xmas(grid, row, col, dx, dy) {
-                    count += 1;
+                    found.push((row, col, dx, dy));
                 }
             }
         }
     }
-    count
+    found
 }
 
-fn check_xmas(grid: &Vec<Vec<char>>, row: usize, col: usize, dx: i32, dy: i32) -> bool {
-    let word = "XMAS";
-    let rows = grid.len() as i32;
-    let cols = grid[0].len() as i32;
+// Draws the grid with highlighted cells emphasised and every other cell dimmed
+// to '.', like the worked examples in the puzzle text.
+fn render_grid(grid: &[Vec<char>], highlighted: &[Vec<bool>], render: Render) -> String {
+    let mut out = String::new();
+    if let Render::Html = render {
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<style>\n");
+        out.push_str("pre { color: #666; }\n.match { color: #ffff66; font-weight: bold; }\n");
+        out.push_str("</style>\n</head>\n<body>\n<pre>\n");
+    }
+    for (row, line) in grid.iter().enumerate() {
+        for (col, &c) in line.iter().enumerate() {
+            match (render, highlighted[row][col]) {
+                (Render::Ansi, true) => out.push_str(&format!("\x1b[1;33m{}\x1b[0m", c)),
+                (Render::Ansi, false) => out.push_str("\x1b[2m.\x1b[0m"),
+                (Render::Html, true) => {
+                    let escaped = match c {
+                        '&' => "&amp;".to_string(),
+                        '<' => "&lt;".to_string(),
+                        '>' => "&gt;".to_string(),
+                        _ => c.to_string(),
+                    };
+                    out.push_str(&format!("<span class=\"match\">{}</span>", escaped));
+                }
+                (Render::Html, false) => out.push('.'),
+            }
+        }
+        out.push('\n');
+    }
+    if let Render::Html = render {
+        out.push_str("</pre>\n</body>\n</html>\n");
+    }
+    out
+}
 
+fn check_xmas(grid: &[Vec<char>], row: usize, col: usize, dx: i32, dy: i32) -> bool {
+    let word = "XMAS";
     for (i, expected_char) in word.chars().enumerate() {
         let new_row = row as i32 + dx * i as i32;
         let new_col = col as i32 + dy * i as i32;
 
-        if new_row < 0 || new_row >= rows || new_col < 0 || new_col >= cols {
+        if new_row < 0 || new_col < 0 {
             return false;
         }
 
-        if grid[new_row as usize][new_col as usize] != expected_char {
+        let cell = grid
+            .get(new_row as usize)
+            .and_then(|line| line.get(new_col as usize));
+        if cell != Some(&expected_char) {
             return false;
         }
     }
     true
 }
 
-fn part2(file_path: &str) {
-    let input = std::fs::read_to_string(file_path).expect("Failed to read file");
-    let grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-    let rows = grid.len();
-    let cols = grid[0].len();
-
-    let mut sum = 0;
-
-    // valid patterns correspond to top-left, top-right, bottom-left, bottom-right
-    const VALID_PATTERNS: [(char, char, char, char); 4] = [
-        ('M', 'M', 'S', 'S'),
-        ('S', 'M', 'S', 'M'),
-        ('S', 'S', 'M', 'M'),
-        ('M', 'S', 'M', 'S'),
-    ];
-
-    for row in 1..rows - 1 {
-        for col in 1..cols - 1 {
-            if grid[row][col] != 'A' {
-                continue;
+fn part2(file_path: &str, render: Option<Render>) {
+    let grid = load_grid(file_path);
+    let matches = find_pattern(&grid, &Pattern::parse(XMAS_PATTERN), &TRANSFORMS);
+    let Some(render) = render else {
+        println!("{}", matches.len());
+        return;
+    };
+
+    let mut highlighted = grid
+        .iter()
+        .map(|line| vec![false; line.len()])
+        .collect::<Vec<_>>();
+    for m in &matches {
+        for &(row, col) in &m.cells {
+            highlighted[row][col] = true;
+        }
+    }
+    print!("{}", render_grid(&grid, &highlighted, render));
+}
+
+fn pattern(file_path: &str, pattern_path: &str, transforms: Option<&str>) {
+    let grid = load_grid(file_path);
+    let pattern = std::fs::read_to_string(pattern_path).expect("Failed to read pattern");
+    let pattern = Pattern::parse(&pattern);
+
+    let transforms: Vec<&'static str> = match transforms {
+        None => TRANSFORMS.to_vec(),
+        Some(names) => names
+            .split(',')
+            .map(|name| {
+                let Some(&transform) = TRANSFORMS.iter().find(|&&t| t == name) else {
+                    eprintln!(
+                        "Invalid transform: {}. Use any of {}.",
+                        name,
+                        TRANSFORMS.join(", ")
+                    );
+                    std::process::exit(1);
+                };
+                transform
+            })
+            .collect(),
+    };
+
+    let matches = find_pattern(&grid, &pattern, &transforms);
+    for m in &matches {
+        println!("{} {} {}", m.row, m.col, m.transform);
+    }
+    println!("{}", matches.len());
+}
+
+// A small 2D template where None matches any cell. Written as text with '.'
+// as the wildcard; ragged rows are padded with wildcards.
+#[derive(Clone, PartialEq)]
+struct Pattern {
+    cells: Vec<Vec<Option<char>>>,
+}
+
+impl Pattern {
+    fn parse(text: &str) -> Pattern {
+        let mut cells: Vec<Vec<Option<char>>> = text
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| if c == '.' { None } else { Some(c) })
+                    .collect()
+            })
+            .collect();
+        let width = cells.iter().map(Vec::len).max().unwrap_or(0);
+        for row in &mut cells {
+            row.resize(width, None);
+        }
+        Pattern { cells }
+    }
+
+    fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn width(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+
+    // Rotates a quarter turn clockwise.
+    fn rotate(&self) -> Pattern {
+        let height = self.height();
+        let cells = (0..self.width())
+            .map(|col| {
+                (0..height)
+                    .map(|row| self.cells[height - 1 - row][col])
+                    .collect()
+            })
+            .collect();
+        Pattern { cells }
+    }
+
+    // Mirrors left-right.
+    fn flip(&self) -> Pattern {
+        let cells = self
+            .cells
+            .iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+        Pattern { cells }
+    }
+
+    fn transformed(&self, transform: &str) -> Pattern {
+        let index = TRANSFORMS
+            .iter()
+            .position(|&t| t == transform)
+            .expect("Unknown transform");
+        let mut pattern = if index >= 4 {
+            self.flip()
+        } else {
+            self.clone()
+        };
+        for _ in 0..index % 4 {
+            pattern = pattern.rotate();
+        }
+        pattern
+    }
+}
+
+struct PatternMatch {
+    row: usize,
+    col: usize,
+    transform: &'static str,
+    // The grid cells covered by the pattern's non-wildcard cells.
+    cells: Vec<(usize, usize)>,
+}
+
+// Finds every placement of the pattern under the given transforms, reported by
+// the top-left corner of the transformed pattern. The whole template must fit
+// on the grid, wildcards included, so every transform of it treats the edges
+// alike. Transforms that produce the same template (e.g. every rotation of a
+// symmetric pattern) are only tried once so a placement is never counted
+// twice.
+fn find_pattern(
+    grid: &[Vec<char>],
+    pattern: &Pattern,
+    transforms: &[&'static str],
+) -> Vec<PatternMatch> {
+    let mut variants: Vec<(&'static str, Pattern)> = Vec::new();
+    for &transform in transforms {
+        let variant = pattern.transformed(transform);
+        if !variants.iter().any(|(_, seen)| *seen == variant) {
+            variants.push((transform, variant));
+        }
+    }
+
+    let mut matches = Vec::new();
+    for row in 0..grid.len() {
+        for col in 0..grid[row].len() {
+            for (transform, variant) in &variants {
+                let matched = variant.cells.iter().enumerate().all(|(dr, line)| {
+                    line.iter().enumerate().all(|(dc, expected)| {
+                        grid.get(row + dr)
+                            .and_then(|line| line.get(col + dc))
+                            .is_some_and(|c| expected.is_none_or(|e| e == *c))
+                    })
+                });
+                if matched && variant.height() > 0 && variant.width() > 0 {
+                    let cells = variant
+                        .cells
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(dr, line)| {
+                            line.iter()
+                                .enumerate()
+                                .filter(|(_, expected)| expected.is_some())
+                                .map(move |(dc, _)| (row + dr, col + dc))
+                        })
+                        .collect();
+                    matches.push(PatternMatch {
+                        row,
+                        col,
+                        transform,
+                        cells,
+                    });
+                }
             }
+        }
+    }
+    matches
+}
 
-            let corners = [
-                grid[row - 1][col - 1], // top-left
-                grid[row - 1][col + 1], // top-right
-                grid[row + 1][col - 1], // bottom-left
-                grid[row + 1][col + 1], // bottom-right
-            ];
+fn words(file_path: &str, dictionary_path: &str) {
+    let grid = load_grid(file_path);
+    let dictionary = std::fs::read_to_string(dictionary_path).expect("Failed to read dictionary");
+    let dictionary: Vec<&str> = dictionary
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .collect();
 
-            if VALID_PATTERNS.contains(&(corners[0], corners[1], corners[2], corners[3])) {
-                sum += 1;
+    let matches = find_words(&grid, &dictionary);
+    for m in &matches {
+        println!("{} {} {} {}", m.row, m.col, m.direction, m.word);
+    }
+    println!("{}", matches.len());
+}
+
+struct WordMatch<'a> {
+    row: usize,
+    col: usize,
+    direction: &'static str,
+    word: &'a str,
+}
+
+// Finds every dictionary word in all 8 directions. Each direction splits the
+// grid into straight rays that are fed through a single Aho-Corasick automaton,
+// so the cost doesn't grow with the number of words.
+fn find_words<'a>(grid: &[Vec<char>], dictionary: &[&'a str]) -> Vec<WordMatch<'a>> {
+    let automaton = AhoCorasick::new(dictionary).expect("Failed to build automaton");
+    let rows = grid.len() as i32;
+    let in_bounds = |row: i32, col: i32| {
+        row >= 0 && row < rows && col >= 0 && (col as usize) < grid[row as usize].len()
+    };
+
+    let mut matches = Vec::new();
+    for &(dx, dy, direction) in &DIRECTIONS {
+        for row in 0..rows {
+            for col in 0..grid[row as usize].len() as i32 {
+                // Only start a ray from cells that have no predecessor in this
+                // direction, so every cell is visited once per direction.
+                if in_bounds(row - dx, col - dy) {
+                    continue;
+                }
+
+                let mut ray = String::new();
+                let mut cells = Vec::new();
+                let (mut r, mut c) = (row, col);
+                while in_bounds(r, c) {
+                    cells.push((ray.len(), r as usize, c as usize));
+                    ray.push(grid[r as usize][c as usize]);
+                    r += dx;
+                    c += dy;
+                }
+
+                for found in automaton.find_overlapping_iter(&ray) {
+                    let start = cells
+                        .binary_search_by_key(&found.start(), |&(offset, _, _)| offset)
+                        .expect("Match starts inside a character");
+                    let (_, row, col) = cells[start];
+                    matches.push(WordMatch {
+                        row,
+                        col,
+                        direction,
+                        word: dictionary[found.pattern().as_usize()],
+                    });
+                }
             }
         }
     }
-    println!("{}", sum);
+    matches.sort_by_key(|m| (m.row, m.col));
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_edges() {
+        let grid = parse_grid("AB\nCX").unwrap();
+        // Wildcards can't hang off any edge, whichever corner the letter is in
+        for template in ["X.\n..", "..\n.X", ".X\n..", "..\nX."] {
+            let found = find_pattern(&grid, &Pattern::parse(template), &["identity"]);
+            let expected = usize::from(template == "..\n.X");
+            assert_eq!(found.len(), expected, "{:?}", template);
+        }
+        // All four rotations of the corner pattern agree on that
+        let found = find_pattern(&grid, &Pattern::parse("X.\n.."), &TRANSFORMS);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].row, found[0].col), (0, 0));
+        assert_eq!(found[0].cells, [(1, 1)]);
+    }
 }
@@ -1,23 +1,46 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
 use std::env;
+use std::fmt;
 use std::fs;
+use std::io::{self, BufRead};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 3 {
-        eprintln!("Usage: {} <part1|part2> <file_path>", args[0]);
+        eprintln!(
+            "Usage: {0} <part1|part2> <file_path> [--show] [--transitive]\n       {0} check <rules_path> [--transitive] < updates\n       {0} audit <file_path>",
+            args[0]
+        );
         std::process::exit(1);
     }
 
     let command = &args[1];
     let file_path = &args[2];
+    let mut show = false;
+    let mut transitive = false;
+    for flag in &args[3..] {
+        match flag.as_str() {
+            "--show" => show = true,
+            "--transitive" => transitive = true,
+            _ => {
+                eprintln!("Invalid option: {}. Use '--show' or '--transitive'.", flag);
+                std::process::exit(1);
+            }
+        }
+    }
 
     let start = std::time::Instant::now();
     match command.as_str() {
-        "part1" => part1(file_path),
-        "part2" => part2(file_path),
+        "part1" => part1(file_path, transitive),
+        "part2" => part2(file_path, show, transitive),
+        "check" => check(file_path, transitive),
+        "audit" => audit(file_path),
         _ => {
-            eprintln!("Invalid command: {}. Use 'part1' or 'part2'.", command);
+            eprintln!(
+                "Invalid command: {}. Use 'part1', 'part2', 'check' or 'audit'.",
+                command
+            );
             std::process::exit(1);
         }
     }
@@ -25,120 +48,546 @@ fn main() {
     eprintln!("Time: {:.6} seconds", duration.as_secs_f64());
 }
 
-fn part1(file_path: &str) {
-    // Read the file content
-    let content = fs::read_to_string(file_path).expect("Failed to read file");
-    let sections: Vec<&str> = content.split("\n\n").collect();
+// Ordering rules compiled once into a dense bitmap over the pages they
+// mention: bit b of row a is set when page a must come before page b.
+#[derive(Clone)]
+struct RuleSet {
+    index: HashMap<u32, usize>,
+    pages: Vec<u32>,
+    words: usize,
+    after: Vec<u64>,
+}
 
-    // Parse ordering rules
-    let mut rules: HashMap<u32, HashSet<u32>> = HashMap::new();
-    for line in sections[0].lines() {
-        let parts: Vec<u32> = line.split('|').map(|s| s.parse().unwrap()).collect();
-        rules
-            .entry(parts[0])
-            .or_insert_with(HashSet::new)
-            .insert(parts[1]);
+impl RuleSet {
+    // Parses the "X|Y" rules section of the input.
+    fn parse(section: &str) -> RuleSet {
+        let rules: Vec<(u32, u32)> = section
+            .lines()
+            .map(|line| {
+                let (before, after) = line.split_once('|').expect("Invalid rule");
+                (before.parse().unwrap(), after.parse().unwrap())
+            })
+            .collect();
+        RuleSet::from_rules(rules)
     }
 
-    // Check each update
-    let mut sum_of_middle_pages = 0;
-    for line in sections[1].lines() {
-        let pages: Vec<u32> = line.split(',').map(|s| s.parse().unwrap()).collect();
-        if is_correct_order(&pages, &rules) {
-            let middle_index = pages.len() / 2;
-            sum_of_middle_pages += pages[middle_index];
+    fn from_rules(rules: Vec<(u32, u32)>) -> RuleSet {
+        let mut index = HashMap::new();
+        let mut pages = Vec::new();
+        for &(before, after) in &rules {
+            for page in [before, after] {
+                index.entry(page).or_insert_with(|| {
+                    pages.push(page);
+                    pages.len() - 1
+                });
+            }
+        }
+
+        let words = pages.len().div_ceil(64);
+        let mut rule_set = RuleSet {
+            after: vec![0; pages.len() * words],
+            index,
+            pages,
+            words,
+        };
+        for (before, after) in rules {
+            let (i, j) = (rule_set.index[&before], rule_set.index[&after]);
+            rule_set.after[i * words + j / 64] |= 1 << (j % 64);
         }
+        rule_set
     }
 
-    println!("{}", sum_of_middle_pages);
-}
+    // Adds every rule implied by chaining others, so that X|Y and Y|Z also
+    // forbid Z appearing before X. Floyd-Warshall over bitmap rows.
+    fn transitive_closure(mut self) -> RuleSet {
+        let (n, words) = (self.index.len(), self.words);
+        for k in 0..n {
+            for i in 0..n {
+                if self.has(i, k) {
+                    for w in 0..words {
+                        self.after[i * words + w] |= self.after[k * words + w];
+                    }
+                }
+            }
+        }
+        self
+    }
 
-fn is_correct_order(pages: &[u32], rules: &HashMap<u32, HashSet<u32>>) -> bool {
-    for (i, &page) in pages.iter().enumerate() {
-        for &next_page in &pages[i + 1..] {
-            // Check if current page must come after next_page
-            if let Some(after_pages) = rules.get(&next_page) {
-                if after_pages.contains(&page) {
-                    return false;
+    // The transitive closure, unless some page must come before itself, in
+    // which case the implied rules contradict each other and the cycle is
+    // returned instead.
+    fn acyclic_closure(&self) -> Result<RuleSet, CycleError> {
+        let closure = self.clone().transitive_closure();
+        let Some(i) = (0..self.pages.len()).find(|&i| closure.has(i, i)) else {
+            return Ok(closure);
+        };
+        // Some page after this one leads back to it
+        let page = self.pages[i];
+        let next = (0..self.pages.len())
+            .find(|&j| self.has(i, j) && closure.has(j, i))
+            .unwrap();
+        let mut cycle = vec![page];
+        if let Some(mut chain) = self.path(self.pages[next], page, false) {
+            chain.pop();
+            cycle.extend(chain);
+        }
+        Err(CycleError { cycle })
+    }
+
+    // Whether the page at index i must come before the page at index j.
+    fn has(&self, i: usize, j: usize) -> bool {
+        self.after[i * self.words + j / 64] & (1 << (j % 64)) != 0
+    }
+
+    fn must_precede(&self, before: u32, after: u32) -> bool {
+        match (self.index.get(&before), self.index.get(&after)) {
+            (Some(&i), Some(&j)) => self.has(i, j),
+            _ => false,
+        }
+    }
+
+    fn is_ordered(&self, pages: &[u32]) -> bool {
+        self.first_violation(pages).is_none()
+    }
+
+    // A single pass that keeps a bitmap of pages seen so far; a page is out of
+    // order if it must precede any of them. Linear in the update length for a
+    // fixed rule set. Returns the (earlier, later) pages that break a rule.
+    fn first_violation(&self, pages: &[u32]) -> Option<(u32, u32)> {
+        let mut seen = vec![0u64; self.words];
+        for &page in pages {
+            let Some(&i) = self.index.get(&page) else {
+                continue;
+            };
+            let row = &self.after[i * self.words..(i + 1) * self.words];
+            for (w, (after, seen)) in row.iter().zip(&seen).enumerate() {
+                let clash = after & seen;
+                if clash != 0 {
+                    let j = w * 64 + clash.trailing_zeros() as usize;
+                    return Some((self.pages[j], page));
+                }
+            }
+            seen[i / 64] |= 1 << (i % 64);
+        }
+        None
+    }
+
+    // The shortest chain of rules leading from one page to another, found by
+    // breadth-first search, optionally without using the direct rule between
+    // them. Both ends are included.
+    fn path(&self, from: u32, to: u32, skip_direct: bool) -> Option<Vec<u32>> {
+        let (&start, &goal) = (self.index.get(&from)?, self.index.get(&to)?);
+        let mut came_from = vec![None; self.pages.len()];
+        let mut queue = VecDeque::from([start]);
+        while let Some(i) = queue.pop_front() {
+            for j in (0..self.pages.len()).filter(|&j| self.has(i, j)) {
+                let skipped = skip_direct && i == start && j == goal;
+                if came_from[j].is_some() || j == start || skipped {
+                    continue;
                 }
+                came_from[j] = Some(i);
+                if j == goal {
+                    let mut path = vec![self.pages[goal]];
+                    let mut current = goal;
+                    while let Some(prev) = came_from[current] {
+                        path.push(self.pages[prev]);
+                        if prev == start {
+                            break;
+                        }
+                        current = prev;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                queue.push_back(j);
             }
         }
+        None
     }
-    true
 }
 
-fn part2(file_path: &str) {
-    // Read the file content
+fn join_chain(pages: &[u32]) -> String {
+    pages
+        .iter()
+        .map(|page| page.to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+// What auditing a rules file against its updates found.
+struct Audit {
+    // Pages that must come before themselves by chaining rules
+    cyclic_pages: usize,
+    // Updates that follow every direct rule but break one implied by chaining
+    // rules across the file, with the chain they break. Left empty when the
+    // rules are cyclic, since the implied rules then contradict each other.
+    transitive_only: Vec<(usize, Vec<u32>)>,
+    // Rules some other chain of rules already implies, with that chain
+    redundant: Vec<Vec<u32>>,
+    // Rules that lie on a cycle, with the cycle back to their first page.
+    // Rules on a cycle imply each other, so none of them is redundant on its
+    // own.
+    cyclic_rules: Vec<Vec<u32>>,
+}
+
+fn audit_rules(direct: &RuleSet, updates: &[Vec<u32>]) -> Audit {
+    let closure = direct.clone().transitive_closure();
+    let cyclic_pages = (0..direct.pages.len())
+        .filter(|&i| closure.has(i, i))
+        .count();
+
+    let mut transitive_only = Vec::new();
+    if cyclic_pages == 0 {
+        for (i, pages) in updates.iter().enumerate() {
+            if !direct.is_ordered(pages) {
+                continue;
+            }
+            if let Some((earlier, later)) = closure.first_violation(pages) {
+                let chain = direct.path(later, earlier, false).unwrap_or_default();
+                transitive_only.push((i, chain));
+            }
+        }
+    }
+
+    // A rule X|Y is redundant when Y can still be reached from X without it.
+    let mut redundant = Vec::new();
+    let mut cyclic_rules = Vec::new();
+    for (i, &before) in direct.pages.iter().enumerate() {
+        for (j, &after) in direct.pages.iter().enumerate() {
+            if !direct.has(i, j) {
+                continue;
+            }
+            if closure.has(j, i) {
+                let mut cycle = vec![before];
+                cycle.extend(direct.path(after, before, false).unwrap_or_default());
+                cyclic_rules.push(cycle);
+            } else if let Some(chain) = direct.path(before, after, true) {
+                redundant.push(chain);
+            }
+        }
+    }
+
+    Audit {
+        cyclic_pages,
+        transitive_only,
+        redundant,
+        cyclic_rules,
+    }
+}
+
+// Audits a hand-maintained rules file against its updates: which updates only
+// break rules implied by chaining others, which rules add nothing because a
+// chain of other rules already implies them, and which rules form cycles.
+fn audit(file_path: &str) {
+    let (direct, updates) = read_input(file_path, false);
+    let found = audit_rules(&direct, &updates);
+
+    if found.cyclic_pages > 0 {
+        println!(
+            "Rules are cyclic through {} page(s); implied rules contradict each other, so updates aren't checked against them",
+            found.cyclic_pages
+        );
+    }
+    for (i, chain) in &found.transitive_only {
+        println!(
+            "Update {} ({}) breaks implied rule {}|{} via {}",
+            i + 1,
+            join_pages(&updates[*i]),
+            chain[0],
+            chain[chain.len() - 1],
+            join_chain(chain)
+        );
+    }
+    for chain in &found.redundant {
+        println!(
+            "Rule {}|{} is implied by {}",
+            chain[0],
+            chain[chain.len() - 1],
+            join_chain(chain)
+        );
+    }
+    for cycle in &found.cyclic_rules {
+        println!(
+            "Rule {}|{} is on the cycle {}",
+            cycle[0],
+            cycle[1],
+            join_chain(cycle)
+        );
+    }
+
+    println!(
+        "{} update(s) only fail transitively, {} redundant rule(s), {} rule(s) on cycles",
+        found.transitive_only.len(),
+        found.redundant.len(),
+        found.cyclic_rules.len()
+    );
+}
+
+fn parse_update(line: &str) -> Vec<u32> {
+    line.split(',').map(|s| s.parse().unwrap()).collect()
+}
+
+// Compiles the rules section, closing it over chained rules with
+// --transitive. Rules that chain into a cycle can't be closed, so that is an
+// error rather than a silent fall back to the direct rules.
+fn compile_rules(section: &str, transitive: bool) -> RuleSet {
+    let rules = RuleSet::parse(section);
+    if !transitive {
+        return rules;
+    }
+    rules.acyclic_closure().unwrap_or_else(|err| {
+        eprintln!("Cannot apply --transitive: {}", err);
+        std::process::exit(1);
+    })
+}
+
+// Reads the rules and the updates, compiling the rules exactly once.
+fn read_input(file_path: &str, transitive: bool) -> (RuleSet, Vec<Vec<u32>>) {
     let content = fs::read_to_string(file_path).expect("Failed to read file");
-    let sections: Vec<&str> = content.split("\n\n").collect();
+    let (rules, updates) = content.split_once("\n\n").expect("Missing updates section");
 
-    // Parse ordering rules
-    let mut rules: HashMap<u32, HashSet<u32>> = HashMap::new();
-    for line in sections[0].lines() {
-        let parts: Vec<u32> = line.split('|').map(|s| s.parse().unwrap()).collect();
-        rules
-            .entry(parts[0])
-            .or_insert_with(HashSet::new)
-            .insert(parts[1]);
+    let rules = compile_rules(rules, transitive);
+    let updates = updates
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| parse_update(l.trim()))
+        .collect();
+    (rules, updates)
+}
+
+fn part1(file_path: &str, transitive: bool) {
+    let (rules, updates) = read_input(file_path, transitive);
+
+    // Check each update
+    let mut sum_of_middle_pages = 0;
+    for pages in &updates {
+        if rules.is_ordered(pages) {
+            let middle_index = pages.len() / 2;
+            sum_of_middle_pages += pages[middle_index];
+        }
     }
 
+    println!("{}", sum_of_middle_pages);
+}
+
+fn part2(file_path: &str, show: bool, transitive: bool) {
+    let (rules, updates) = read_input(file_path, transitive);
+
     // Check each update and reorder if necessary
     let mut sum_of_middle_pages = 0;
-    for line in sections[1].lines() {
-        let pages: Vec<u32> = line.split(',').map(|s| s.parse().unwrap()).collect();
-        if !is_correct_order(&pages, &rules) {
-            let sorted_pages = topological_sort(&pages, &rules);
-            let middle_index = sorted_pages.len() / 2;
-            sum_of_middle_pages += sorted_pages[middle_index];
+    let mut failed = 0;
+    for (i, pages) in updates.iter().enumerate() {
+        if !rules.is_ordered(pages) {
+            match topological_sort(pages, &rules) {
+                Ok(sorted_pages) => {
+                    if show {
+                        println!("{} -> {}", join_pages(pages), join_pages(&sorted_pages));
+                    }
+                    let middle_index = sorted_pages.len() / 2;
+                    sum_of_middle_pages += sorted_pages[middle_index];
+                }
+                Err(err) => {
+                    eprintln!(
+                        "Update {} ({}) cannot be ordered: {}",
+                        i + 1,
+                        join_pages(pages),
+                        err
+                    );
+                    failed += 1;
+                }
+            }
         }
     }
 
     println!("{}", sum_of_middle_pages);
+    if failed > 0 {
+        eprintln!("{} update(s) skipped due to cyclic rules", failed);
+        std::process::exit(1);
+    }
 }
 
-fn topological_sort(pages: &[u32], rules: &HashMap<u32, HashSet<u32>>) -> Vec<u32> {
-    let mut in_degree = HashMap::new();
-    let mut graph = HashMap::new();
+// Validates updates read line by line from stdin against a rules file, which
+// may be a full puzzle input since only its first section is used.
+fn check(rules_path: &str, transitive: bool) {
+    let content = fs::read_to_string(rules_path).expect("Failed to read file");
+    let section = content.split("\n\n").next().unwrap_or_default();
+    let rules = compile_rules(section, transitive);
 
-    // Initialize graph and in-degree count
-    for &page in pages {
-        in_degree.entry(page).or_insert(0);
-        graph.entry(page).or_insert_with(HashSet::new);
+    for line in io::stdin().lock().lines() {
+        let line = line.expect("Failed to read stdin");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let pages = parse_update(line.trim());
+        let verdict = if rules.is_ordered(&pages) {
+            "valid"
+        } else {
+            "invalid"
+        };
+        println!("{}", verdict);
     }
+}
 
-    // Build graph and in-degree count
-    for &page in pages {
-        if let Some(after_pages) = rules.get(&page) {
-            for &after_page in after_pages {
-                if pages.contains(&after_page) {
-                    graph.get_mut(&page).unwrap().insert(after_page);
-                    *in_degree.entry(after_page).or_insert(0) += 1;
-                }
+// Rules that require these pages to each come before the next, with the last
+// one coming before the first again.
+#[derive(Debug)]
+struct CycleError {
+    cycle: Vec<u32>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rules form a cycle ")?;
+        for page in &self.cycle {
+            write!(f, "{} -> ", page)?;
+        }
+        write!(f, "{}", self.cycle[0])
+    }
+}
+
+fn join_pages(pages: &[u32]) -> String {
+    pages
+        .iter()
+        .map(|page| page.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Kahn's algorithm that always places the ready page that came earliest in the
+// update, so pages the rules don't constrain keep their original relative
+// order and the result is fully deterministic.
+fn topological_sort(pages: &[u32], rules: &RuleSet) -> Result<Vec<u32>, CycleError> {
+    let mut in_degree = vec![0; pages.len()];
+    let mut graph = vec![Vec::new(); pages.len()];
+
+    // Build graph and in-degree count over positions in the update
+    for (i, &page) in pages.iter().enumerate() {
+        for (j, &after_page) in pages.iter().enumerate() {
+            if rules.must_precede(page, after_page) {
+                graph[i].push(j);
+                in_degree[j] += 1;
             }
         }
     }
 
-    // Topological sort using Kahn's algorithm
     let mut sorted = Vec::new();
-    let mut queue: Vec<u32> = in_degree
-        .iter()
-        .filter(|&(_, &deg)| deg == 0)
-        .map(|(&page, _)| page)
+    let mut ready: BinaryHeap<Reverse<usize>> = (0..pages.len())
+        .filter(|&i| in_degree[i] == 0)
+        .map(Reverse)
         .collect();
 
-    while let Some(page) = queue.pop() {
-        sorted.push(page);
-        if let Some(neighbors) = graph.get(&page) {
-            for &neighbor in neighbors {
-                if let Some(deg) = in_degree.get_mut(&neighbor) {
-                    *deg -= 1;
-                    if *deg == 0 {
-                        queue.push(neighbor);
-                    }
-                }
+    while let Some(Reverse(i)) = ready.pop() {
+        sorted.push(pages[i]);
+        for &j in &graph[i] {
+            in_degree[j] -= 1;
+            if in_degree[j] == 0 {
+                ready.push(Reverse(j));
             }
         }
     }
 
-    sorted
+    if sorted.len() < pages.len() {
+        return Err(find_cycle(pages, &graph, &in_degree));
+    }
+    Ok(sorted)
+}
+
+// Called once Kahn's algorithm stalls. Every page it couldn't place still has a
+// predecessor it couldn't place either, so walking predecessors from any of
+// them must eventually revisit a page, closing a cycle.
+fn find_cycle(pages: &[u32], graph: &[Vec<usize>], in_degree: &[usize]) -> CycleError {
+    let predecessor = |j: usize| {
+        (0..pages.len())
+            .find(|&i| in_degree[i] > 0 && graph[i].contains(&j))
+            .expect("Stalled page has no stalled predecessor")
+    };
+
+    let mut path = vec![(0..pages.len()).find(|&i| in_degree[i] > 0).unwrap()];
+    loop {
+        let prev = predecessor(*path.last().unwrap());
+        if let Some(start) = path.iter().position(|&i| i == prev) {
+            // The path was built walking backwards, so reverse it to read in
+            // rule order.
+            let mut cycle: Vec<u32> = path.split_off(start).iter().map(|&i| pages[i]).collect();
+            cycle.reverse();
+            return CycleError { cycle };
+        }
+        path.push(prev);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const TESTRULES: &str = "47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13";
+
+    #[test]
+    fn test_transitive_rules() {
+        // 1|2 and 2|3 imply 1|3, which only the closure enforces
+        let rules = RuleSet::parse("1|2\n2|3");
+        let closure = rules.acyclic_closure().unwrap();
+        assert!(rules.is_ordered(&[3, 1]));
+        assert!(!closure.is_ordered(&[3, 1]));
+        assert!(closure.is_ordered(&[1, 3]));
+
+        let rules = RuleSet::parse(TESTRULES);
+        let closure = rules.acyclic_closure().unwrap();
+        for pages in [[75, 47, 61, 53, 29], [75, 97, 47, 61, 53]] {
+            assert_eq!(closure.is_ordered(&pages), rules.is_ordered(&pages));
+        }
+
+        // Cyclic across the file, as in the real input
+        let cycle = RuleSet::parse("1|2\n2|3\n3|4\n4|2").acyclic_closure().err();
+        assert_eq!(cycle.map(|err| err.cycle), Some(vec![2, 3, 4]));
+        let cycle = RuleSet::parse("5|5").acyclic_closure().err();
+        assert_eq!(cycle.map(|err| err.cycle), Some(vec![5]));
+    }
+
+    #[test]
+    fn test_audit() {
+        let rules = RuleSet::parse(TESTRULES);
+        let found = audit_rules(&rules, &[vec![75, 47, 61, 53, 29]]);
+        assert_eq!(found.cyclic_pages, 0);
+        assert!(found.transitive_only.is_empty());
+        assert_eq!(found.redundant.len(), 15);
+        assert!(found.redundant.contains(&vec![75, 47, 29]));
+        assert!(found.cyclic_rules.is_empty());
+
+        // 3,1 follows both direct rules but not the 1|3 they imply
+        let rules = RuleSet::parse("1|2\n2|3");
+        let found = audit_rules(&rules, &[vec![3, 1], vec![1, 3], vec![3, 2]]);
+        assert_eq!(found.transitive_only, [(0, vec![1, 2, 3])]);
+
+        // 1|3 is implied by 1|2 and 2|3, but 3|4 and 4|5 only imply each
+        // other around the cycle, so they're reported separately
+        let rules = RuleSet::parse("1|2\n2|3\n1|3\n3|4\n4|5\n5|3");
+        let found = audit_rules(&rules, &[vec![1, 2, 3], vec![4, 5, 1]]);
+        assert_eq!(found.cyclic_pages, 3);
+        assert!(found.transitive_only.is_empty());
+        assert_eq!(found.redundant, [vec![1, 2, 3]]);
+        assert_eq!(
+            found.cyclic_rules,
+            [vec![3, 4, 5, 3], vec![4, 5, 3, 4], vec![5, 3, 4, 5]]
+        );
+    }
 }
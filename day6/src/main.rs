@@ -1,20 +1,103 @@
 use aochelpers::{get_daily_input, Coordinate, Direction};
-use std::{collections::HashSet, error::Error};
+use rayon::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    error::Error,
+};
 
+// Every facing in clockwise order, so turning is a step around the ring.
+const COMPASS: [Direction; 8] = [
+    Direction::North,
+    Direction::NorthEast,
+    Direction::East,
+    Direction::SouthEast,
+    Direction::South,
+    Direction::SouthWest,
+    Direction::West,
+    Direction::NorthWest,
+];
+
+// What a guard does when something blocks their way.
+#[derive(Clone, Copy)]
+enum TurnPolicy {
+    Clockwise,
+    CounterClockwise,
+    Reverse,
+    // Clockwise and counter-clockwise in turn, starting clockwise
+    Alternating,
+}
+
+// How guards move. In diagonal mode they turn by 45 degrees instead of 90 and
+// can end up walking diagonally.
+#[derive(Clone, Copy)]
+struct Rules {
+    policy: TurnPolicy,
+    diagonal: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            policy: TurnPolicy::Clockwise,
+            diagonal: false,
+        }
+    }
+}
+
+impl Rules {
+    // The facing after a guard's next turn, given how many turns they've
+    // already made.
+    fn turn(&self, facing: Direction, turns_made: usize) -> Direction {
+        let quarter = if self.diagonal { 1 } else { 2 };
+        let offset = match self.policy {
+            TurnPolicy::Clockwise => quarter,
+            TurnPolicy::CounterClockwise => 8 - quarter,
+            TurnPolicy::Reverse => 4,
+            TurnPolicy::Alternating if turns_made.is_multiple_of(2) => quarter,
+            TurnPolicy::Alternating => 8 - quarter,
+        };
+        COMPASS[(facing_index(facing) + offset) % 8]
+    }
+}
+
+// The map as a dense grid, plus for every facing a jump table giving the cell
+// where a guard walking that way from a given cell stops in front of a wall,
+// or None if they walk off the map.
 struct Arena {
-    walls: HashSet<Coordinate<i32>>,
+    walls: Vec<bool>,
     max_x: i32,
     max_y: i32,
+    jumps: Vec<Vec<Option<usize>>>,
 }
 
 impl Arena {
-    fn new(walls: HashSet<Coordinate<i32>>) -> Self {
-        let max_x = walls.iter().map(|c| c.x).max().unwrap();
-        let max_y = walls.iter().map(|c| c.y).max().unwrap();
-        Self {
-            walls,
-            max_x,
-            max_y,
+    // Takes the map's dimensions explicitly, since edges without any walls
+    // still belong to the map.
+    fn new(walls: HashSet<Coordinate<i32>>, width: i32, height: i32) -> Self {
+        let mut arena = Self {
+            walls: vec![false; (width * height) as usize],
+            max_x: width - 1,
+            max_y: height - 1,
+            jumps: Vec::new(),
+        };
+        for wall in &walls {
+            let index = arena.index(wall);
+            arena.walls[index] = true;
+        }
+        arena.jumps = COMPASS.iter().map(|&f| arena.build_jumps(f)).collect();
+        arena
+    }
+
+    fn index(&self, coord: &Coordinate<i32>) -> usize {
+        (coord.y * (self.max_x + 1) + coord.x) as usize
+    }
+
+    fn coordinate(&self, index: usize) -> Coordinate<i32> {
+        let width = self.max_x as usize + 1;
+        Coordinate {
+            x: (index % width) as i32,
+            y: (index / width) as i32,
         }
     }
 
@@ -23,111 +106,394 @@ impl Arena {
     }
 
     fn contains_wall(&self, coord: &Coordinate<i32>) -> bool {
-        self.walls.contains(coord)
+        self.is_in_bounds(coord) && self.walls[self.index(coord)]
     }
 
-    fn add_wall(&mut self, coord: Coordinate<i32>) {
-        self.walls.insert(coord);
+    // Fills the jump table for one facing, visiting cells furthest along that
+    // facing first so each cell can reuse the answer of the cell ahead of it.
+    fn build_jumps(&self, facing: Direction) -> Vec<Option<usize>> {
+        let step = step(facing);
+        let mut ys: Vec<i32> = (0..=self.max_y).collect();
+        let mut xs: Vec<i32> = (0..=self.max_x).collect();
+        if step.y > 0 {
+            ys.reverse();
+        }
+        if step.x > 0 {
+            xs.reverse();
+        }
+
+        let mut jumps = vec![None; self.walls.len()];
+        for &y in &ys {
+            for &x in &xs {
+                let here = Coordinate { x, y };
+                let ahead = here.neighbour(facing);
+                jumps[self.index(&here)] = if !self.is_in_bounds(&ahead) {
+                    None
+                } else if self.contains_wall(&ahead) {
+                    Some(self.index(&here))
+                } else {
+                    jumps[self.index(&ahead)]
+                };
+            }
+        }
+        jumps
     }
 
-    fn clone_walls(&self) -> HashSet<Coordinate<i32>> {
-        self.walls.clone()
+    // Follows the guard from turn to turn using the jump tables, treating
+    // `obstacle` as an extra wall without modifying the map. A loop is found
+    // once the guard turns at the same cell with the same facing twice, and
+    // with the same turn parity since the alternating policy depends on it.
+    fn is_loop(
+        &self,
+        rules: &Rules,
+        mut guard: Coordinate<i32>,
+        mut facing: Direction,
+        obstacle: Coordinate<i32>,
+    ) -> bool {
+        let mut turns = vec![0u16; self.walls.len()];
+        let mut turns_made = 0;
+        loop {
+            let facing_index = facing_index(facing);
+            let here = self.index(&guard);
+            let state = 1 << (facing_index + 8 * (turns_made % 2));
+            if turns[here] & state != 0 {
+                return true;
+            }
+            turns[here] |= state;
+
+            let stop = self.jumps[facing_index][here].map(|i| self.coordinate(i));
+            let step = step(facing);
+            let (dx, dy) = (obstacle.x - guard.x, obstacle.y - guard.y);
+            // How many steps ahead the obstacle is, if it's on this path at all
+            let k = if step.x != 0 {
+                dx * step.x
+            } else {
+                dy * step.y
+            };
+            let obstacle_distance =
+                (k > 0 && dx == k * step.x && dy == k * step.y).then_some(k as u32);
+            let stop_distance = stop.map(|s| {
+                (s.x - guard.x)
+                    .unsigned_abs()
+                    .max((s.y - guard.y).unsigned_abs())
+            });
+
+            guard = match (obstacle_distance, stop_distance) {
+                (Some(o), None) => walk(guard, step, o - 1),
+                (Some(o), Some(s)) if o <= s => walk(guard, step, o - 1),
+                (_, Some(_)) => stop.unwrap(),
+                (None, None) => return false,
+            };
+            facing = rules.turn(facing, turns_made);
+            turns_made += 1;
+        }
     }
 }
 
+#[derive(Clone, Copy)]
+struct Guard {
+    position: Coordinate<i32>,
+    facing: Direction,
+}
+
+// Where a guard went: the facings they had in each cell they covered, as bits
+// indexed like COMPASS.
+struct Patrol {
+    cells: HashMap<Coordinate<i32>, u8>,
+    // How many steps one lap of the loop takes, if the guard never leaves
+    loop_length: Option<usize>,
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let data = get_daily_input(6, 2024)?;
 
-    let (arena, guard) = parse_data(&data);
-    let (part1, part2) = solve(&arena, guard);
+    let (arena, guards) = parse_data(&data);
+    let mut rules = Rules::default();
+    let mut output = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--draw" | "--json" => output = Some(arg),
+            "--diagonal" => rules.diagonal = true,
+            "--turn=clockwise" => rules.policy = TurnPolicy::Clockwise,
+            "--turn=counter-clockwise" => rules.policy = TurnPolicy::CounterClockwise,
+            "--turn=reverse" => rules.policy = TurnPolicy::Reverse,
+            "--turn=alternating" => rules.policy = TurnPolicy::Alternating,
+            _ => {
+                return Err(format!(
+                    "Invalid option: {}. Use '--draw', '--json', '--diagonal' or '--turn=<clockwise|counter-clockwise|reverse|alternating>'.",
+                    arg
+                )
+                .into())
+            }
+        }
+    }
+    match output.as_deref() {
+        Some("--draw") => {
+            print!("{}", draw(&arena, &rules, &guards));
+            return Ok(());
+        }
+        Some(_) => {
+            println!("{}", obstacles_json(&arena, &rules, &guards));
+            return Ok(());
+        }
+        None => {}
+    }
+
+    for (i, guard) in guards.iter().enumerate() {
+        let patrol = patrol(&arena, &rules, guard, None);
+        println!(
+            "Guard {} at ({}, {}) facing {}: {} cells{}",
+            i + 1,
+            guard.position.x,
+            guard.position.y,
+            facing_symbol(guard.facing),
+            patrol.cells.len(),
+            if patrol.loop_length.is_some() {
+                " (loops)"
+            } else {
+                ""
+            }
+        );
+    }
+    let (part1, part2) = solve(&arena, &rules, &guards);
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
 
     Ok(())
 }
 
-fn rotate_clockwise(direction: Direction) -> Direction {
-    match direction {
-        Direction::North => Direction::East,
-        Direction::East => Direction::South,
-        Direction::South => Direction::West,
-        Direction::West => Direction::North,
-        _ => unimplemented!(),
-    }
+// Guards always start facing a cardinal direction.
+fn facing_symbol(direction: Direction) -> char {
+    ['^', '>', 'v', '<'][facing_index(direction) / 2]
 }
 
-fn solve(arena: &Arena, mut guard: Coordinate<i32>) -> (usize, usize) {
-    let mut current_facing = Direction::North;
-    let mut visited = HashSet::new();
-    let mut possible_blocks = HashSet::new();
-    let starting_point = guard;
+fn facing_index(direction: Direction) -> usize {
+    COMPASS.iter().position(|&f| f == direction).unwrap()
+}
 
-    while arena.is_in_bounds(&guard) {
-        visited.insert(guard);
-        while arena.contains_wall(&guard.neighbour(current_facing)) {
-            current_facing = rotate_clockwise(current_facing);
-        }
-        let next_guard_location = guard.neighbour(current_facing);
+// The offset of a single step in the given direction.
+fn step(direction: Direction) -> Coordinate<i32> {
+    Coordinate { x: 0, y: 0 }.neighbour(direction)
+}
+
+fn walk(from: Coordinate<i32>, step: Coordinate<i32>, steps: u32) -> Coordinate<i32> {
+    Coordinate {
+        x: from.x + step.x * steps as i32,
+        y: from.y + step.y * steps as i32,
+    }
+}
 
-        let mut parallel_universe = Arena::new(arena.clone_walls());
-        parallel_universe.add_wall(next_guard_location);
+// Walks a guard step by step until they leave the map or repeat a state,
+// optionally with one extra obstacle placed on the map.
+fn patrol(
+    arena: &Arena,
+    rules: &Rules,
+    guard: &Guard,
+    obstacle: Option<Coordinate<i32>>,
+) -> Patrol {
+    let blocked = |c: Coordinate<i32>| arena.contains_wall(&c) || Some(c) == obstacle;
+    let mut position = guard.position;
+    let mut current_facing = guard.facing;
+    let mut turns_made = 0;
+    let mut cells: HashMap<Coordinate<i32>, u8> = HashMap::new();
+    let mut states = HashMap::new();
 
-        if !possible_blocks.contains(&next_guard_location)
-            && next_guard_location != starting_point
-            && is_loop(&parallel_universe, starting_point)
-            && arena.is_in_bounds(&next_guard_location)
-        {
-            possible_blocks.insert(next_guard_location);
+    while arena.is_in_bounds(&position) {
+        let step = states.len();
+        let state = (position, current_facing, turns_made % 2);
+        if let Some(first) = states.insert(state, step) {
+            return Patrol {
+                cells,
+                loop_length: Some(step - first),
+            };
         }
-        guard = guard.neighbour(current_facing);
+        let facings = cells.entry(position).or_default();
+        *facings |= 1 << facing_index(current_facing);
+        let mut attempts = 0;
+        while blocked(position.neighbour(current_facing)) {
+            // Every facing and parity has been tried, so the guard is boxed
+            // in and will spin on the spot forever
+            if attempts == 16 {
+                return Patrol {
+                    cells,
+                    loop_length: Some(0),
+                };
+            }
+            current_facing = rules.turn(current_facing, turns_made);
+            turns_made += 1;
+            attempts += 1;
+            *facings |= 1 << facing_index(current_facing);
+        }
+        position = position.neighbour(current_facing);
+    }
+    Patrol {
+        cells,
+        loop_length: None,
     }
-    (visited.len(), possible_blocks.len())
 }
 
-fn is_loop(arena: &Arena, mut guard: Coordinate<i32>) -> bool {
-    let mut visited: HashSet<(Coordinate<i32>, Direction)> = HashSet::new();
-    let mut current_facing: Direction = Direction::North;
+// Every place a single obstacle would trap at least one guard who otherwise
+// leaves the map, in reading order. Guards patrol independently and never
+// block each other.
+fn find_blocks(
+    arena: &Arena,
+    rules: &Rules,
+    guards: &[Guard],
+    patrols: &[Patrol],
+) -> Vec<Coordinate<i32>> {
+    // Only cells on an escaping guard's path can change where they go, and
+    // no obstacle can be placed where a guard is standing
+    let candidates: Vec<Coordinate<i32>> = patrols
+        .iter()
+        .filter(|patrol| patrol.loop_length.is_none())
+        .flat_map(|patrol| patrol.cells.keys().copied())
+        .filter(|c| guards.iter().all(|guard| guard.position != *c))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let mut blocks: Vec<Coordinate<i32>> = candidates
+        .into_par_iter()
+        .filter(|&c| {
+            guards.iter().zip(patrols).any(|(guard, patrol)| {
+                patrol.loop_length.is_none()
+                    && patrol.cells.contains_key(&c)
+                    && arena.is_loop(rules, guard.position, guard.facing, c)
+            })
+        })
+        .collect();
+    blocks.sort_by_key(|c| (c.y, c.x));
+    blocks
+}
 
-    while arena.is_in_bounds(&guard) {
-        if visited.contains(&(guard, current_facing)) {
-            return true;
-        }
-        visited.insert((guard, current_facing));
-        while arena.contains_wall(&guard.neighbour(current_facing)) {
-            current_facing = rotate_clockwise(current_facing);
+// Returns the number of cells covered by any guard, and the number of places
+// an obstacle would trap at least one of them.
+fn solve(arena: &Arena, rules: &Rules, guards: &[Guard]) -> (usize, usize) {
+    let patrols: Vec<Patrol> = guards
+        .iter()
+        .map(|guard| patrol(arena, rules, guard, None))
+        .collect();
+    let covered: HashSet<Coordinate<i32>> = patrols
+        .iter()
+        .flat_map(|patrol| patrol.cells.keys().copied())
+        .collect();
+
+    (
+        covered.len(),
+        find_blocks(arena, rules, guards, &patrols).len(),
+    )
+}
+
+// Draws the map the way the puzzle text does: '|' and '-' where guards walked
+// north-south and east-west, '\\' and '/' along diagonals, '+' where they went
+// more than one way, and 'O' wherever an obstacle would trap a guard.
+fn draw(arena: &Arena, rules: &Rules, guards: &[Guard]) -> String {
+    let patrols: Vec<Patrol> = guards
+        .iter()
+        .map(|guard| patrol(arena, rules, guard, None))
+        .collect();
+    let blocks: HashSet<Coordinate<i32>> = find_blocks(arena, rules, guards, &patrols)
+        .into_iter()
+        .collect();
+    let axis = |a: Direction, b: Direction| 1u8 << facing_index(a) | 1 << facing_index(b);
+    let strokes = [
+        (axis(Direction::North, Direction::South), '|'),
+        (axis(Direction::East, Direction::West), '-'),
+        (axis(Direction::NorthWest, Direction::SouthEast), '\\'),
+        (axis(Direction::NorthEast, Direction::SouthWest), '/'),
+    ];
+
+    let mut out = String::new();
+    for y in 0..=arena.max_y {
+        for x in 0..=arena.max_x {
+            let c = Coordinate { x, y };
+            let facings = patrols
+                .iter()
+                .filter_map(|patrol| patrol.cells.get(&c))
+                .fold(0, |acc, f| acc | f);
+            let walked: Vec<char> = strokes
+                .iter()
+                .filter(|(mask, _)| facings & mask != 0)
+                .map(|&(_, stroke)| stroke)
+                .collect();
+            out.push(if blocks.contains(&c) {
+                'O'
+            } else if arena.contains_wall(&c) {
+                '#'
+            } else if let Some(guard) = guards.iter().find(|guard| guard.position == c) {
+                facing_symbol(guard.facing)
+            } else {
+                match walked[..] {
+                    [] => '.',
+                    [stroke] => stroke,
+                    _ => '+',
+                }
+            });
         }
-        guard = guard.neighbour(current_facing);
+        out.push('\n');
     }
-    false
+    out
 }
 
-fn parse_data(input: &str) -> (Arena, Coordinate<i32>) {
+// Lists every trapping obstacle with the loop it causes for each guard it
+// traps, measured in steps around the loop.
+fn obstacles_json(arena: &Arena, rules: &Rules, guards: &[Guard]) -> String {
+    let patrols: Vec<Patrol> = guards
+        .iter()
+        .map(|guard| patrol(arena, rules, guard, None))
+        .collect();
+    let obstacles: Vec<String> = find_blocks(arena, rules, guards, &patrols)
+        .into_iter()
+        .map(|block| {
+            let loops: Vec<String> = guards
+                .iter()
+                .zip(&patrols)
+                .enumerate()
+                .filter(|(_, (_, original))| {
+                    original.loop_length.is_none() && original.cells.contains_key(&block)
+                })
+                .filter_map(|(i, (guard, _))| {
+                    patrol(arena, rules, guard, Some(block))
+                        .loop_length
+                        .map(|length| format!("{{\"guard\":{},\"length\":{}}}", i + 1, length))
+                })
+                .collect();
+            format!(
+                "{{\"x\":{},\"y\":{},\"loops\":[{}]}}",
+                block.x,
+                block.y,
+                loops.join(",")
+            )
+        })
+        .collect();
+    format!("{{\"obstacles\":[{}]}}", obstacles.join(","))
+}
+
+fn parse_data(input: &str) -> (Arena, Vec<Guard>) {
     let mut walls = HashSet::new();
-    let mut guard_location = Coordinate {
-        x: i32::MAX,
-        y: i32::MAX,
-    };
+    let height = input.lines().count() as i32;
+    let width = input.lines().map(|line| line.len()).max().unwrap_or(0) as i32;
+    let mut guards = Vec::new();
     for (y, line) in input.lines().enumerate() {
         for (x, c) in line.chars().enumerate() {
+            let position = Coordinate {
+                x: x as i32,
+                y: y as i32,
+            };
             match c {
                 '#' => {
-                    walls.insert(Coordinate {
-                        x: x as i32,
-                        y: y as i32,
-                    });
+                    walls.insert(position);
                 }
-                '^' => {
-                    guard_location = Coordinate {
-                        x: x as i32,
-                        y: y as i32,
-                    };
+                '^' | '>' | 'v' | '<' => {
+                    let facing =
+                        COMPASS[2 * ['^', '>', 'v', '<'].iter().position(|&f| f == c).unwrap()];
+                    guards.push(Guard { position, facing });
                 }
                 _ => {}
             }
         }
     }
 
-    (Arena::new(walls), guard_location)
+    (Arena::new(walls, width, height), guards)
 }
 
 #[cfg(test)]
@@ -146,13 +512,104 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let (arena, guard) = parse_data(TESTDATA);
-        assert_eq!(solve(&arena, guard).0, 41);
+        let (arena, guards) = parse_data(TESTDATA);
+        assert_eq!(solve(&arena, &Rules::default(), &guards).0, 41);
     }
 
     #[test]
     fn test_part2() {
-        let (arena, guard) = parse_data(TESTDATA);
-        assert_eq!(solve(&arena, guard).1, 6);
+        let (arena, guards) = parse_data(TESTDATA);
+        assert_eq!(solve(&arena, &Rules::default(), &guards).1, 6);
+    }
+
+    #[test]
+    fn test_wall_free_edges() {
+        // The sample with extra columns and rows that contain no walls
+        let padded = "....#........
+.........#...
+.............
+..#..........
+.......#.....
+.............
+.#..^........
+........#....
+#............
+......#......
+.............
+.............";
+        let (arena, guards) = parse_data(padded);
+        assert_eq!(solve(&arena, &Rules::default(), &guards), (43, 6));
+
+        let (arena, guards) = parse_data(".#...\n....#\n.....\n.^...\n.....\n.....");
+        assert_eq!(solve(&arena, &Rules::default(), &guards), (9, 0));
+    }
+
+    #[test]
+    fn test_facings_and_multiple_guards() {
+        // The sample guard turned to face west
+        let (arena, guards) = parse_data(&TESTDATA.replace('^', "<"));
+        assert_eq!(solve(&arena, &Rules::default(), &guards), (26, 6));
+
+        let (arena, guards) = parse_data(
+            "....#.....
+.........#
+..........
+..#.......
+.......#..
+......<...
+.#..^.....
+........#.
+#.........
+v.....#...",
+        );
+        assert_eq!(guards.len(), 3);
+        assert_eq!(
+            patrol(&arena, &Rules::default(), &guards[0], None)
+                .cells
+                .len(),
+            7
+        );
+        assert_eq!(solve(&arena, &Rules::default(), &guards), (46, 6));
+    }
+
+    #[test]
+    fn test_turn_policies() {
+        let rules = |policy, diagonal| Rules { policy, diagonal };
+
+        // Turning counter-clockwise on the mirrored map retraces the sample
+        let mirrored: Vec<String> = TESTDATA
+            .lines()
+            .map(|line| line.chars().rev().collect())
+            .collect();
+        let (arena, guards) = parse_data(&mirrored.join("\n"));
+        let counter_clockwise = rules(TurnPolicy::CounterClockwise, false);
+        assert_eq!(solve(&arena, &counter_clockwise, &guards), (41, 6));
+
+        let (arena, guards) = parse_data(TESTDATA);
+        let reverse = rules(TurnPolicy::Reverse, false);
+        assert_eq!(solve(&arena, &reverse, &guards), (9, 3));
+        let alternating = rules(TurnPolicy::Alternating, false);
+        assert_eq!(solve(&arena, &alternating, &guards), (11, 2));
+        let diagonal = rules(TurnPolicy::Alternating, true);
+        assert_eq!(solve(&arena, &diagonal, &guards), (7, 1));
+    }
+
+    #[test]
+    fn test_draw() {
+        let (arena, guards) = parse_data(TESTDATA);
+        assert_eq!(
+            draw(&arena, &Rules::default(), &guards),
+            "....#.....
+....+---+#
+....|...|.
+..#.|...|.
+..+-+-+#|.
+..|.|.|.|.
+.#+O^-+-+.
+.+----OO#.
+#O-O--+|..
+......#O..
+"
+        );
     }
 }
@@ -1,147 +1,572 @@
-use aochelpers::{get_daily_input, ScoredItem};
-use std::{
-    collections::{BinaryHeap, HashSet},
-    error::Error,
+use aochelpers::get_daily_input;
+use num_bigint::BigInt;
+use num_traits::{
+    checked_pow, CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Signed, ToPrimitive,
 };
+use std::{collections::HashMap, env, error::Error, fmt::Display, hash::Hash, ops, str::FromStr};
+
+// The largest power `^` will build, in bits. Fixed-width types overflow long
+// before this.
+const MAX_POWER_BITS: f64 = 65536.0;
+
+// Anything equations can be evaluated in. Every operation is checked, so a
+// branch that doesn't fit is dropped rather than wrapping or panicking.
+// Numbers are signed so that `-` can go below zero part way through.
+trait Number:
+    Clone
+    + Ord
+    + Hash
+    + Display
+    + FromStr
+    + Signed
+    + From<u8>
+    + ToPrimitive
+    + CheckedAdd
+    + CheckedSub
+    + CheckedMul
+    + CheckedDiv
+    + ops::Div<Output = Self>
+    + ops::Rem<Output = Self>
+{
+}
+
+impl<T> Number for T where
+    T: Clone
+        + Ord
+        + Hash
+        + Display
+        + FromStr
+        + Signed
+        + From<u8>
+        + ToPrimitive
+        + CheckedAdd
+        + CheckedSub
+        + CheckedMul
+        + CheckedDiv
+        + ops::Div<Output = T>
+        + ops::Rem<Output = T>
+{
+}
+
+// Which number type to parse equations into. Wider types are slower, so the
+// puzzle input sticks with i64.
+enum Precision {
+    I64,
+    I128,
+    Big,
+}
 
 #[derive(Debug, Clone)]
-struct Equation {
-    target: i64,
-    numbers: Vec<i64>,
+struct Equation<N> {
+    target: N,
+    numbers: Vec<N>,
+}
+
+// What to print for each solvable equation instead of the puzzle answers.
+enum Output {
+    // One satisfying assignment
+    Show,
+    // Every satisfying assignment
+    All,
+    // How many assignments satisfy it
+    Count,
+}
+
+// Something that can sit between two operands. Operators that can be undone
+// let the solver work backwards from the target, which prunes far more than
+// trying every assignment forwards.
+trait Operator<N> {
+    fn symbol(&self) -> &'static str;
+    // The result, or None if it doesn't fit or isn't defined
+    fn apply(&self, left: &N, right: &N) -> Option<N>;
+    // Whether the left operand can be recovered from the result when the
+    // right operand is `right`
+    fn invertible(&self, _right: &N) -> bool {
+        false
+    }
+    // Every left operand that gives `result` with `right`. Only called when
+    // `invertible` is true.
+    fn unapply(&self, _result: &N, _right: &N) -> Vec<N> {
+        Vec::new()
+    }
+    // Whether `result` comes out with `right` whatever the left operand is,
+    // so any value the operands before it can produce will do
+    fn absorbs(&self, _result: &N, _right: &N) -> bool {
+        false
+    }
+}
+
+struct Add;
+struct Multiply;
+struct Concat;
+struct Subtract;
+struct Divide;
+struct Power;
+struct Max;
+
+impl<N: Number> Operator<N> for Add {
+    fn symbol(&self) -> &'static str {
+        "+"
+    }
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_add(right)
+    }
+    fn invertible(&self, _right: &N) -> bool {
+        true
+    }
+    fn unapply(&self, result: &N, right: &N) -> Vec<N> {
+        result.checked_sub(right).into_iter().collect()
+    }
+}
+
+impl<N: Number> Operator<N> for Multiply {
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_mul(right)
+    }
+    fn invertible(&self, _right: &N) -> bool {
+        true
+    }
+    // Anything times zero is zero, which `absorbs` covers
+    fn unapply(&self, result: &N, right: &N) -> Vec<N> {
+        result
+            .checked_div(right)
+            .filter(|left| left.checked_mul(right).as_ref() == Some(result))
+            .into_iter()
+            .collect()
+    }
+    fn absorbs(&self, result: &N, right: &N) -> bool {
+        result.is_zero() && right.is_zero()
+    }
+}
+
+impl<N: Number> Operator<N> for Concat {
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+    // Digits only join up for numbers that aren't negative
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        if left.is_negative() || right.is_negative() {
+            return None;
+        }
+        left.checked_mul(&digit_shift(right)?)?.checked_add(right)
+    }
+    fn invertible(&self, _right: &N) -> bool {
+        true
+    }
+    fn unapply(&self, result: &N, right: &N) -> Vec<N> {
+        if result.is_negative() || right.is_negative() {
+            return Vec::new();
+        }
+        match digit_shift(right) {
+            Some(shift) if result.clone() % shift.clone() == *right => {
+                vec![result.clone() / shift]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+// Values never go negative, so subtracting past zero rules a branch out.
+impl<N: Number> Operator<N> for Subtract {
+    fn symbol(&self) -> &'static str {
+        "-"
+    }
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_sub(right)
+    }
+    fn invertible(&self, _right: &N) -> bool {
+        true
+    }
+    fn unapply(&self, result: &N, right: &N) -> Vec<N> {
+        result.checked_add(right).into_iter().collect()
+    }
+}
+
+impl<N: Number> Operator<N> for Divide {
+    fn symbol(&self) -> &'static str {
+        "/"
+    }
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_div(right)
+    }
+}
+
+impl<N: Number> Operator<N> for Power {
+    fn symbol(&self) -> &'static str {
+        "^"
+    }
+    // Results past MAX_POWER_BITS are dropped like an overflow, so a huge
+    // exponent can't stall arbitrary precision
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        let exponent = right.to_usize()?;
+        let magnitude = left.to_f64()?.abs();
+        if magnitude > 1.0 && magnitude.log2() * exponent as f64 > MAX_POWER_BITS {
+            return None;
+        }
+        checked_pow(left.clone(), exponent)
+    }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
-struct PartialSolution {
-    current_value: i64,
-    ops_used: usize,
-    ops: Vec<char>,
+impl<N: Number> Operator<N> for Max {
+    fn symbol(&self) -> &'static str {
+        "max"
+    }
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        Some(left.max(right).clone())
+    }
+}
+
+// Every operator that can be picked with `--ops`. New operators only need an
+// entry here.
+fn registry<N: Number>() -> Vec<Box<dyn Operator<N>>> {
+    vec![
+        Box::new(Add),
+        Box::new(Multiply),
+        Box::new(Concat),
+        Box::new(Subtract),
+        Box::new(Divide),
+        Box::new(Power),
+        Box::new(Max),
+    ]
+}
+
+// The operators a search may use, tried in this order.
+struct OperatorSet<N> {
+    operators: Vec<Box<dyn Operator<N>>>,
+}
+
+impl<N: Number> OperatorSet<N> {
+    // `+` and `*`, plus `||` for part 2
+    fn puzzle(allow_concat: bool) -> Self {
+        let spec = if allow_concat { "+,*,||" } else { "+,*" };
+        Self::parse(spec).unwrap()
+    }
+
+    // Picks operators out of the registry by symbol, e.g. "+,*,||,-".
+    fn parse(spec: &str) -> Result<Self, String> {
+        let mut available = registry();
+        let mut operators = Vec::new();
+        for symbol in spec.split(',').map(str::trim) {
+            match available.iter().position(|op| op.symbol() == symbol) {
+                Some(i) => operators.push(available.swap_remove(i)),
+                None if operators.iter().any(|op| op.symbol() == symbol) => {}
+                None => {
+                    let known: Vec<&str> = registry::<N>().iter().map(|op| op.symbol()).collect();
+                    return Err(format!(
+                        "Unknown operator: '{}'. Choose from {}.",
+                        symbol,
+                        known.join(" ")
+                    ));
+                }
+            }
+        }
+        Ok(OperatorSet { operators })
+    }
+
+    // Whether every step of `eq` can be undone, whichever operator it uses
+    fn invertible(&self, eq: &Equation<N>) -> bool {
+        eq.numbers[1..]
+            .iter()
+            .all(|n| self.operators.iter().all(|op| op.invertible(n)))
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let data = get_daily_input(7, 2024)?;
 
-    let equations = parse_data(&data);
-    let (part1, part2) = solve(&equations);
+    let mut output = None;
+    let mut allow_concat = false;
+    let mut ops = None;
+    let mut precision = Precision::I64;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--show" => output = Some(Output::Show),
+            "--all" => output = Some(Output::All),
+            "--count" => output = Some(Output::Count),
+            "--concat" => allow_concat = true,
+            "--ops" => ops = Some(args.next().ok_or("'--ops' needs a list like '+,*,||,-'")?),
+            "--precision=i64" => precision = Precision::I64,
+            "--precision=i128" => precision = Precision::I128,
+            "--precision=big" => precision = Precision::Big,
+            _ => {
+                return Err(format!(
+                    "Invalid option: {}. Use '--show', '--all', '--count', '--concat', '--ops <list>' or '--precision=<i64|i128|big>'.",
+                    arg
+                )
+                .into())
+            }
+        }
+    }
+
+    match precision {
+        Precision::I64 => run::<i64>(&data, output, allow_concat, ops.as_deref()),
+        Precision::I128 => run::<i128>(&data, output, allow_concat, ops.as_deref()),
+        Precision::Big => run::<BigInt>(&data, output, allow_concat, ops.as_deref()),
+    }
+}
+
+// Prints the answers for both puzzle parts, or for one chosen operator set.
+// `allow_concat` picks the puzzle set the output modes use when no set is
+// chosen.
+fn run<N: Number>(
+    data: &str,
+    output: Option<Output>,
+    allow_concat: bool,
+    ops: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    const OVERFLOW: &str = "The total overflows. Try '--precision=i128' or '--precision=big'.";
+    let equations = parse_data::<N>(data)?;
+    let set = ops.map(OperatorSet::parse).transpose()?;
+    if let Some(output) = output {
+        let set = set.unwrap_or_else(|| OperatorSet::puzzle(allow_concat));
+        for eq in &equations {
+            match output {
+                Output::Show => {
+                    if let Some(ops) = solve_equation(eq, &set) {
+                        println!("{}", render(eq, &ops));
+                    }
+                }
+                Output::All => {
+                    for ops in all_solutions(eq, &set) {
+                        println!("{}", render(eq, &ops));
+                    }
+                }
+                Output::Count => {
+                    let count = count_solutions(eq, &set);
+                    if count > 0 {
+                        println!("{}: {}", eq.target, count);
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(set) = set {
+        println!("Total: {}", total(&equations, &set).ok_or(OVERFLOW)?);
+        return Ok(());
+    }
+    let (part1, part2) = solve(&equations).ok_or(OVERFLOW)?;
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
 
     Ok(())
 }
 
-fn solve(equations: &[Equation]) -> (i64, i64) {
-    let part1: i64 = equations
-        .iter()
-        .filter(|eq| solve_equation(eq, false))
-        .map(|eq| eq.target)
-        .sum();
+// Sums the targets of the solvable equations for each part, or None if a
+// total doesn't fit.
+fn solve<N: Number>(equations: &[Equation<N>]) -> Option<(N, N)> {
+    Some((
+        total(equations, &OperatorSet::puzzle(false))?,
+        total(equations, &OperatorSet::puzzle(true))?,
+    ))
+}
 
-    let part2: i64 = equations
+fn total<N: Number>(equations: &[Equation<N>], set: &OperatorSet<N>) -> Option<N> {
+    equations
         .iter()
-        .filter(|eq| solve_equation(eq, true))
-        .map(|eq| eq.target)
-        .sum();
-
-    (part1, part2)
+        .filter(|eq| solve_equation(eq, set).is_some())
+        .try_fold(N::zero(), |sum, eq| sum.checked_add(&eq.target))
 }
 
-fn parse_data(input: &str) -> Vec<Equation> {
+fn parse_data<N: Number>(input: &str) -> Result<Vec<Equation<N>>, String> {
+    let parse = |line_number: usize, text: &str| {
+        text.parse().map_err(|_| {
+            format!(
+                "Line {}: '{}' isn't a number that fits the chosen precision",
+                line_number, text
+            )
+        })
+    };
     input
         .lines()
-        .map(|line| {
-            let (target, nums) = line.split_once(": ").unwrap();
-            Equation {
-                target: target.parse().unwrap(),
-                numbers: nums
-                    .split_whitespace()
-                    .map(|n| n.parse().unwrap())
-                    .collect(),
+        .enumerate()
+        .map(|(i, line)| {
+            let (target, nums) = line
+                .split_once(": ")
+                .ok_or_else(|| format!("Line {}: expected '<target>: <numbers>'", i + 1))?;
+            let target = parse(i + 1, target)?;
+            let numbers: Vec<N> = nums
+                .split_whitespace()
+                .map(|n| parse(i + 1, n))
+                .collect::<Result<_, _>>()?;
+            if numbers.is_empty() {
+                return Err(format!("Line {}: expected at least one number", i + 1));
             }
+            Ok(Equation { target, numbers })
         })
         .collect()
 }
 
-fn solve_equation(eq: &Equation, allow_concat: bool) -> bool {
-    let mut heap = BinaryHeap::new();
-    let mut seen = HashSet::new();
-
-    // Start with first number
-    let initial = PartialSolution {
-        current_value: eq.numbers[0],
-        ops_used: 0,
-        ops: vec![],
-    };
+// The power of ten that shifts a number left past all of `n`'s digits, if it
+// fits.
+fn digit_shift<N: Number>(n: &N) -> Option<N> {
+    let ten = N::from(10);
+    let mut shift = ten.clone();
+    while shift <= *n {
+        shift = shift.checked_mul(&ten)?;
+    }
+    Some(shift)
+}
 
-    heap.push(ScoredItem {
-        cost: (eq.target - initial.current_value).abs(),
-        item: initial,
-    });
+// Writes an equation out with its operators, e.g. `3267 = 81 * 40 + 27`.
+fn render<N: Number>(eq: &Equation<N>, ops: &[&str]) -> String {
+    let mut out = format!("{} = {}", eq.target, eq.numbers[0]);
+    for (op, number) in ops.iter().zip(&eq.numbers[1..]) {
+        out.push_str(&format!(" {} {}", op, number));
+    }
+    out
+}
 
-    while let Some(ScoredItem { item: partial, .. }) = heap.pop() {
-        if !seen.insert((partial.current_value, partial.ops_used)) {
-            continue;
+// Finds one operator assignment that makes the equation true, if any. When
+// every step can be undone this works backwards from the target through
+// the operands, so every operator that can't have produced the value so far
+// cuts off its whole subtree.
+fn solve_equation<N: Number>(eq: &Equation<N>, set: &OperatorSet<N>) -> Option<Vec<&'static str>> {
+    fn unwind<N: Number>(
+        numbers: &[N],
+        set: &OperatorSet<N>,
+        target: N,
+        chosen: &mut Vec<&'static str>,
+    ) -> bool {
+        let (last, rest) = numbers.split_last().unwrap();
+        if rest.is_empty() {
+            return target == *last;
         }
-
-        if partial.ops_used == eq.numbers.len() - 1 {
-            if partial.current_value == eq.target {
-                return true;
+        for op in &set.operators {
+            if op.absorbs(&target, last) {
+                if let Some(prefix) = any_assignment(rest, set) {
+                    chosen.push(op.symbol());
+                    chosen.extend(prefix.into_iter().rev());
+                    return true;
+                }
+                continue;
+            }
+            for left in op.unapply(&target, last) {
+                chosen.push(op.symbol());
+                if unwind(rest, set, left, chosen) {
+                    return true;
+                }
+                chosen.pop();
             }
-            continue;
         }
+        false
+    }
 
-        let next_num = eq.numbers[partial.ops_used + 1];
+    // Any assignment of operators between `numbers` that evaluates at all
+    fn any_assignment<N: Number>(numbers: &[N], set: &OperatorSet<N>) -> Option<Vec<&'static str>> {
+        let (first, rest) = numbers.split_first().unwrap();
+        let Some((next, rest)) = rest.split_first() else {
+            return Some(Vec::new());
+        };
+        set.operators.iter().find_map(|op| {
+            let value = op.apply(first, next)?;
+            let mut numbers = vec![value];
+            numbers.extend_from_slice(rest);
+            let mut chosen = vec![op.symbol()];
+            chosen.extend(any_assignment(&numbers, set)?);
+            Some(chosen)
+        })
+    }
+
+    if !set.invertible(eq) {
+        return search_forward(eq, set, Some(1)).pop();
+    }
+    let mut chosen = Vec::new();
+    unwind(&eq.numbers, set, eq.target.clone(), &mut chosen).then(|| {
+        chosen.reverse();
+        chosen
+    })
+}
+
+// Every operator assignment that makes the equation true, in the order the
+// operators are tried. Branches that overflow are dropped.
+fn all_solutions<N: Number>(eq: &Equation<N>, set: &OperatorSet<N>) -> Vec<Vec<&'static str>> {
+    search_forward(eq, set, None)
+}
 
-        // Try addition
-        let mut new_ops = partial.ops.clone();
-        new_ops.push('+');
-        let add_solution = PartialSolution {
-            current_value: partial.current_value + next_num,
-            ops_used: partial.ops_used + 1,
-            ops: new_ops,
+// Tries assignments from the first operand onwards, stopping once `limit`
+// solutions have been found.
+fn search_forward<N: Number>(
+    eq: &Equation<N>,
+    set: &OperatorSet<N>,
+    limit: Option<usize>,
+) -> Vec<Vec<&'static str>> {
+    fn extend<N: Number>(
+        eq: &Equation<N>,
+        set: &OperatorSet<N>,
+        value: N,
+        chosen: &mut Vec<&'static str>,
+        found: &mut Vec<Vec<&'static str>>,
+        limit: Option<usize>,
+    ) {
+        let Some(next_num) = eq.numbers.get(chosen.len() + 1) else {
+            if value == eq.target {
+                found.push(chosen.clone());
+            }
+            return;
         };
-        heap.push(ScoredItem {
-            cost: (eq.target - add_solution.current_value).abs(),
-            item: add_solution,
-        });
-
-        // Try multiplication
-        let mut new_ops = partial.ops.clone();
-        new_ops.push('*');
-        let mul_solution = PartialSolution {
-            current_value: partial.current_value * next_num,
-            ops_used: partial.ops_used + 1,
-            ops: new_ops,
+        for op in &set.operators {
+            if limit == Some(found.len()) {
+                return;
+            }
+            if let Some(next) = op.apply(&value, next_num) {
+                chosen.push(op.symbol());
+                extend(eq, set, next, chosen, found, limit);
+                chosen.pop();
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+    extend(
+        eq,
+        set,
+        eq.numbers[0].clone(),
+        &mut Vec::new(),
+        &mut found,
+        limit,
+    );
+    found
+}
+
+// Counts the satisfying assignments without listing them, sharing work
+// between prefixes that reach the same value.
+fn count_solutions<N: Number>(eq: &Equation<N>, set: &OperatorSet<N>) -> usize {
+    fn count<N: Number>(
+        eq: &Equation<N>,
+        set: &OperatorSet<N>,
+        value: N,
+        used: usize,
+        cache: &mut HashMap<(N, usize), usize>,
+    ) -> usize {
+        let Some(next_num) = eq.numbers.get(used + 1) else {
+            return usize::from(value == eq.target);
         };
-        heap.push(ScoredItem {
-            cost: (eq.target - mul_solution.current_value).abs(),
-            item: mul_solution,
-        });
-
-        // Try concatenation if allowed
-        if allow_concat {
-            let mut new_ops = partial.ops.clone();
-            new_ops.push('|');
-            // Convert current value to string, append next number, parse back to i64
-            let concat_value = format!("{}{}", partial.current_value, next_num)
-                .parse()
-                .unwrap();
-            let concat_solution = PartialSolution {
-                current_value: concat_value,
-                ops_used: partial.ops_used + 1,
-                ops: new_ops,
-            };
-            heap.push(ScoredItem {
-                cost: (eq.target - concat_solution.current_value).abs(),
-                item: concat_solution,
-            });
+        if let Some(&known) = cache.get(&(value.clone(), used)) {
+            return known;
         }
+        let total = set
+            .operators
+            .iter()
+            .filter_map(|op| op.apply(&value, next_num))
+            .map(|next| count(eq, set, next, used + 1, cache))
+            .sum();
+        cache.insert((value, used), total);
+        total
     }
 
-    false
+    count(eq, set, eq.numbers[0].clone(), 0, &mut HashMap::new())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num_traits::Zero;
     const TESTDATA: &str = "190: 10 19
 3267: 81 40 27
 83: 17 5
@@ -152,15 +577,159 @@ mod tests {
 21037: 9 7 18 13
 292: 11 6 16 20";
 
+    // Runs an assignment forwards, to check solutions that aren't unique
+    fn evaluate<N: Number>(eq: &Equation<N>, set: &OperatorSet<N>, ops: &[&str]) -> Option<N> {
+        ops.iter()
+            .zip(&eq.numbers[1..])
+            .try_fold(eq.numbers[0].clone(), |acc, (&symbol, n)| {
+                let op = set.operators.iter().find(|op| op.symbol() == symbol)?;
+                op.apply(&acc, n)
+            })
+    }
+
     #[test]
     fn test_part1() {
-        let equations = parse_data(TESTDATA);
-        assert_eq!(solve(&equations).0, 3749);
+        let equations = parse_data::<i64>(TESTDATA).unwrap();
+        assert_eq!(solve(&equations).unwrap().0, 3749);
     }
 
     #[test]
     fn test_part2() {
-        let equations = parse_data(TESTDATA);
-        assert_eq!(solve(&equations).1, 11387);
+        let equations = parse_data::<i64>(TESTDATA).unwrap();
+        assert_eq!(solve(&equations).unwrap().1, 11387);
+    }
+
+    #[test]
+    fn test_assignments() {
+        let equations = parse_data::<i64>(TESTDATA).unwrap();
+        let (part1, part2) = (OperatorSet::puzzle(false), OperatorSet::puzzle(true));
+        let rendered: Vec<String> = all_solutions(&equations[1], &part1)
+            .iter()
+            .map(|ops| render(&equations[1], ops))
+            .collect();
+        assert_eq!(rendered, ["3267 = 81 + 40 * 27", "3267 = 81 * 40 + 27"]);
+
+        let ops = solve_equation(&equations[4], &part2).unwrap();
+        assert_eq!(render(&equations[4], &ops), "7290 = 6 * 8 || 6 * 15");
+        assert_eq!(solve_equation(&equations[2], &part2), None);
+
+        let counts: Vec<usize> = equations
+            .iter()
+            .map(|eq| count_solutions(eq, &part2))
+            .collect();
+        assert_eq!(counts, [1, 2, 0, 1, 1, 0, 1, 0, 1]);
+
+        // Multiplying by zero gives zero whatever came before it
+        let equations = parse_data::<i64>("0: 5 0\n7: 5 0 7\n0: 9223372036854775807 2 0").unwrap();
+        let rendered: Vec<Option<String>> = equations
+            .iter()
+            .map(|eq| solve_equation(eq, &part1).map(|ops| render(eq, &ops)))
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                Some("0 = 5 * 0".to_string()),
+                Some("7 = 5 * 0 + 7".to_string()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_equation() {
+        let equations = parse_data::<i64>(
+            "6069943114700: 15 6 19 5 20 11 19 28 23 7 5 20 20 22 8 13 5 19 24 4 20 3 21 8",
+        )
+        .unwrap();
+        let eq = &equations[0];
+        let part2 = OperatorSet::puzzle(true);
+        let ops = solve_equation(eq, &part2).unwrap();
+        assert_eq!(evaluate(eq, &part2, &ops), Some(eq.target));
+        assert_eq!(solve_equation(eq, &OperatorSet::puzzle(false)), None);
+    }
+
+    #[test]
+    fn test_overflow() {
+        // Every concatenation and most products overflow an i64 here
+        let part2 = OperatorSet::puzzle(true);
+        let equations =
+            parse_data::<i64>("9223372036854775807: 2323823089 3969050863 99999 1").unwrap();
+        assert_eq!(count_solutions(&equations[0], &part2), 0);
+        assert_eq!(solve_equation(&equations[0], &part2), None);
+        assert_eq!(solve(&equations), Some((0, 0)));
+        let equations = parse_data::<i64>("9223372036854775807: 2323823089 3969050863").unwrap();
+        assert_eq!(all_solutions(&equations[0], &part2), [["*"]]);
+
+        assert_eq!(
+            parse_data::<i64>("5: 5\n5: ").unwrap_err(),
+            "Line 2: expected at least one number"
+        );
+
+        let input = "123456789012345678901234567890: 1234567890 1234567890 1234567890";
+        assert!(parse_data::<i64>(input).is_err());
+        let equations = parse_data::<i128>(input).unwrap();
+        let part2 = OperatorSet::puzzle(true);
+        assert_eq!(
+            solve_equation(&equations[0], &part2),
+            Some(vec!["||", "||"])
+        );
+
+        let input = "12345678901234567890123456789012345678901234567890: \
+            1234567890 1234567890 1234567890 1234567890 1234567890";
+        assert!(parse_data::<i128>(input).is_err());
+        let equations = parse_data::<BigInt>(input).unwrap();
+        assert_eq!(
+            count_solutions(&equations[0], &OperatorSet::puzzle(true)),
+            1
+        );
+        let (part1, part2) = solve(&equations).unwrap();
+        assert_eq!(
+            (part1, part2),
+            (BigInt::zero(), equations[0].target.clone())
+        );
+    }
+
+    #[test]
+    fn test_operator_sets() {
+        let equations = parse_data::<i64>(TESTDATA).unwrap();
+        let set = OperatorSet::parse("max,-,*,||").unwrap();
+        assert_eq!(total(&equations, &set), Some(7636));
+        let ops = solve_equation(&equations[4], &set).unwrap();
+        assert_eq!(render(&equations[4], &ops), "7290 = 6 * 8 || 6 * 15");
+        assert_eq!(solve_equation(&equations[1], &set), None);
+        let set = OperatorSet::parse("/,*").unwrap();
+        assert_eq!(total(&equations, &set), Some(190));
+
+        // A huge exponent is dropped rather than built
+        let equations = parse_data::<BigInt>("2: 9 4000000000\n1024: 2 10").unwrap();
+        let set = OperatorSet::parse("+,^").unwrap();
+        assert_eq!(solve_equation(&equations[0], &set), None);
+        assert_eq!(solve_equation(&equations[1], &set), Some(vec!["^"]));
+
+        // Undoing `+,-` backwards and trying `+,^,/` forwards agree with
+        // listing every assignment
+        let equations = parse_data::<i64>("5: 2 3 4 4\n16: 2 3 2 0\n1: 2 2 4").unwrap();
+        for spec in ["+,-", "+,^,/"] {
+            let set = OperatorSet::parse(spec).unwrap();
+            for eq in &equations {
+                let ops = solve_equation(eq, &set);
+                assert_eq!(ops.is_some(), !all_solutions(eq, &set).is_empty());
+                if let Some(ops) = ops {
+                    assert_eq!(evaluate(eq, &set, &ops), Some(eq.target));
+                }
+            }
+        }
+
+        // `-` can go below zero part way through
+        let equations = parse_data::<i64>("7: 2 5 10\n3: 1 5 7\n-2: 1 3").unwrap();
+        let set = OperatorSet::parse("+,-").unwrap();
+        let rendered: Vec<String> = equations
+            .iter()
+            .map(|eq| render(eq, &solve_equation(eq, &set).unwrap()))
+            .collect();
+        assert_eq!(rendered, ["7 = 2 - 5 + 10", "3 = 1 - 5 + 7", "-2 = 1 - 3"]);
+        assert_eq!(all_solutions(&equations[0], &set), [["-", "+"]]);
+
+        assert!(OperatorSet::<i64>::parse("+,%").is_err());
     }
 }
@@ -1,14 +1,239 @@
-use aochelpers::{get_daily_input, Coordinate, Rectangle};
+use aochelpers::get_daily_input;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
     error::Error,
 };
 
+// A grid point in any number of dimensions: x, y, then any further axes. Maps
+// stacked in layers add a third axis; more come from coordinate listings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Point(Vec<i32>);
+
+impl Point {
+    // The offset that takes `self` to `other`.
+    fn offset_to(&self, other: &Point) -> Vec<i32> {
+        self.0.iter().zip(&other.0).map(|(a, b)| b - a).collect()
+    }
+
+    // None if the point would be off any grid an i32 can index.
+    fn moved(&self, offset: &[i32], times: i32) -> Option<Point> {
+        self.0
+            .iter()
+            .zip(offset)
+            .map(|(a, d)| a.checked_add(d.checked_mul(times)?))
+            .collect::<Option<_>>()
+            .map(Point)
+    }
+}
+
+// The box of points from `min` to `max` inclusive.
+#[derive(Debug, Clone, PartialEq)]
+struct Bounds {
+    min: Point,
+    max: Point,
+}
+
+impl Bounds {
+    // A box from the origin with `size` points along each axis.
+    fn sized(size: &[i32]) -> Bounds {
+        Bounds {
+            min: Point(vec![0; size.len()]),
+            max: Point(size.iter().map(|s| s - 1).collect()),
+        }
+    }
+
+    fn contains(&self, p: &Point) -> bool {
+        p.0.iter()
+            .zip(self.min.0.iter().zip(&self.max.0))
+            .all(|(v, (min, max))| (min..=max).contains(&v))
+    }
+}
+
+// A position along the line through a pair of antennas, as a fraction of the
+// way from one antenna (0) to the other (1). Each ratio is measured from both
+// antennas in turn, so it doesn't matter which comes first. The puzzle's part
+// 1 antinodes sit at -1 and 2, where one antenna is twice as far as the other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ratio {
+    numerator: i32,
+    denominator: i32,
+}
+
+impl Ratio {
+    // Parses "2", "-1" or "1/3".
+    fn parse(text: &str) -> Option<Ratio> {
+        let (numerator, denominator) = text.split_once('/').unwrap_or((text, "1"));
+        let denominator = denominator.trim().parse().ok().filter(|&d| d != 0)?;
+        Some(Ratio {
+            numerator: numerator.trim().parse().ok()?,
+            denominator,
+        })
+    }
+
+    // The grid point this far along from `from` by `offset`, if it is one.
+    fn along(&self, from: &Point, offset: &[i32]) -> Option<Point> {
+        let scaled: Option<Vec<i32>> = offset
+            .iter()
+            .map(|d| {
+                let d = d.checked_mul(self.numerator)?;
+                (d % self.denominator == 0).then_some(d / self.denominator)
+            })
+            .collect();
+        from.moved(&scaled?, 1)
+    }
+}
+
+// Where a pair of antennas with the same frequency creates antinodes.
+#[derive(Debug, Clone, PartialEq)]
+enum Rule {
+    // At each of these positions along the pair
+    Ratios(Vec<Ratio>),
+    // At every step along the line through the pair. The step is the offset
+    // between the antennas, or the smallest grid step in that direction when
+    // reducing. `max_harmonics` limits how many whole offsets past either
+    // antenna the line reaches.
+    Line {
+        reduce: bool,
+        max_harmonics: Option<u32>,
+    },
+}
+
+impl Rule {
+    fn part1() -> Rule {
+        Rule::Ratios(vec![
+            Ratio {
+                numerator: -1,
+                denominator: 1,
+            },
+            Ratio {
+                numerator: 2,
+                denominator: 1,
+            },
+        ])
+    }
+
+    fn part2() -> Rule {
+        Rule::Line {
+            reduce: true,
+            max_harmonics: None,
+        }
+    }
+
+    fn antinodes(&self, a1: &Point, a2: &Point, bounds: &Bounds) -> Vec<Point> {
+        let offset = a1.offset_to(a2);
+        // Antennas sharing a point have no line between them
+        if offset.iter().all(|&d| d == 0) {
+            return Vec::new();
+        }
+        match self {
+            Rule::Ratios(ratios) => {
+                let back: Vec<i32> = offset.iter().map(|d| -d).collect();
+                ratios
+                    .iter()
+                    .flat_map(|ratio| [ratio.along(a1, &offset), ratio.along(a2, &back)])
+                    .flatten()
+                    .filter(|c| bounds.contains(c))
+                    .collect()
+            }
+            Rule::Line {
+                reduce,
+                max_harmonics,
+            } => {
+                let divisor = if *reduce {
+                    offset.iter().fold(0, |acc, &d| gcd(acc, d))
+                } else {
+                    1
+                };
+                let step: Vec<i32> = offset.iter().map(|d| d / divisor).collect();
+                let at = |k: i32| a1.moved(&step, k);
+                // Steps from a1 run from -h offsets before it to h offsets
+                // past a2. A limit too large to count in steps is no limit,
+                // since the grid ends first.
+                let steps = |h: u32| i32::try_from(h).ok()?.checked_mul(divisor);
+                let (lowest, highest) = match max_harmonics {
+                    Some(h) => (
+                        steps(*h).map_or(i32::MIN, |k| -k),
+                        h.checked_add(1).and_then(steps).unwrap_or(i32::MAX),
+                    ),
+                    None => (i32::MIN, i32::MAX),
+                };
+                let backwards = (lowest..=0).rev().map_while(at);
+                let forwards = (1..=highest).map_while(at);
+                backwards
+                    .take_while(|c| bounds.contains(c))
+                    .chain(forwards.take_while(|c| bounds.contains(c)))
+                    .collect()
+            }
+        }
+    }
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let data = get_daily_input(8, 2024)?;
 
     let (antennas, bounds) = parse_data(&data);
     let antennas_ref: Vec<_> = antennas.iter().collect();
+
+    let invalid = |arg: &str| {
+        format!(
+            "Invalid option: {}. Use '--map', '--table', '--json', '--ratios=<r,...>', '--line', '--reduce' or '--harmonics=<n>'.",
+            arg
+        )
+    };
+    let mut output = None;
+    let mut ratios = None;
+    let mut line = false;
+    let mut reduce = false;
+    let mut max_harmonics = None;
+    for arg in env::args().skip(1) {
+        if let Some(list) = arg.strip_prefix("--ratios=") {
+            let parsed: Option<Vec<Ratio>> = list.split(',').map(Ratio::parse).collect();
+            ratios = Some(parsed.ok_or_else(|| invalid(&arg))?);
+        } else if let Some(count) = arg.strip_prefix("--harmonics=") {
+            max_harmonics = Some(count.parse().map_err(|_| invalid(&arg))?);
+        } else {
+            match arg.as_str() {
+                "--map" | "--table" | "--json" => output = Some(arg),
+                "--line" => line = true,
+                "--reduce" => reduce = true,
+                _ => return Err(invalid(&arg).into()),
+            }
+        }
+    }
+    let rule = match ratios {
+        Some(ratios) => Some(Rule::Ratios(ratios)),
+        None if line || reduce || max_harmonics.is_some() => Some(Rule::Line {
+            reduce,
+            max_harmonics,
+        }),
+        None => None,
+    };
+
+    if let Some(output) = output {
+        let rule = rule.unwrap_or_else(Rule::part1);
+        match output.as_str() {
+            "--map" => print!("{}", draw(&antennas_ref, &bounds, &rule)),
+            "--table" => print!("{}", report_table(&report(&antennas_ref, &bounds, &rule))),
+            _ => println!("{}", report_json(&report(&antennas_ref, &bounds, &rule))),
+        }
+        return Ok(());
+    }
+    if let Some(rule) = rule {
+        println!(
+            "Antinodes: {}",
+            count_antinodes(&antennas_ref, &bounds, &rule)
+        );
+        return Ok(());
+    }
     let (part1, part2) = solve(&antennas_ref, &bounds);
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
@@ -16,109 +241,223 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn parse_data(input: &str) -> (Vec<(char, Coordinate<i32>)>, Rectangle<i32>) {
+// Reads either a map, with further layers stacked below it after blank lines,
+// or a "size: <extents>" line followed by "<frequency>: <coordinates>" lines.
+fn parse_data(input: &str) -> (Vec<(char, Point)>, Bounds) {
+    match input.strip_prefix("size:") {
+        Some(listing) => parse_coordinates(listing),
+        None => parse_layers(input),
+    }
+}
+
+fn parse_layers(input: &str) -> (Vec<(char, Point)>, Bounds) {
     let mut antennas = Vec::new();
-    let height = input.lines().count() as i32;
-    let width = input.lines().next().unwrap().len() as i32;
-
-    for (y, line) in input.lines().enumerate() {
-        for (x, c) in line.chars().enumerate() {
-            if c != '.' {
-                antennas.push((
-                    c,
-                    Coordinate {
-                        x: x as i32,
-                        y: y as i32,
-                    },
-                ));
+    let layers: Vec<&str> = input.trim_end().split("\n\n").collect();
+    let height = layers[0].lines().count() as i32;
+    let width = layers[0].lines().next().unwrap().len() as i32;
+
+    for (z, layer) in layers.iter().enumerate() {
+        for (y, line) in layer.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c != '.' {
+                    let mut point = vec![x as i32, y as i32];
+                    if layers.len() > 1 {
+                        point.push(z as i32);
+                    }
+                    antennas.push((c, Point(point)));
+                }
             }
         }
     }
 
-    let bounds = Rectangle::new(
-        Coordinate { x: 0, y: 0 },
-        Coordinate {
-            x: width - 1,
-            y: height - 1,
-        },
-    );
+    let mut size = vec![width, height];
+    if layers.len() > 1 {
+        size.push(layers.len() as i32);
+    }
+    (antennas, Bounds::sized(&size))
+}
 
-    (antennas, bounds)
+fn parse_coordinates(listing: &str) -> (Vec<(char, Point)>, Bounds) {
+    let numbers =
+        |text: &str| -> Vec<i32> { text.split(',').map(|n| n.trim().parse().unwrap()).collect() };
+    let mut lines = listing.lines();
+    let size = numbers(lines.next().unwrap());
+    let antennas = lines
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (freq, point) = line.split_once(':').unwrap();
+            let point = numbers(point);
+            assert_eq!(
+                point.len(),
+                size.len(),
+                "Wrong number of coordinates: {}",
+                line
+            );
+            (freq.trim().chars().next().unwrap(), Point(point))
+        })
+        .collect();
+    (antennas, Bounds::sized(&size))
 }
 
-fn solve(antennas: &[&(char, Coordinate<i32>)], bounds: &Rectangle<i32>) -> (usize, usize) {
-    let mut frequency_groups: HashMap<char, Vec<&Coordinate<i32>>> = HashMap::new();
-    let mut antinodes_p1: HashSet<Coordinate<i32>> = HashSet::new();
-    let mut antinodes_p2: HashSet<Coordinate<i32>> = HashSet::new();
+fn solve(antennas: &[&(char, Point)], bounds: &Bounds) -> (usize, usize) {
+    (
+        count_antinodes(antennas, bounds, &Rule::part1()),
+        count_antinodes(antennas, bounds, &Rule::part2()),
+    )
+}
+
+fn count_antinodes(antennas: &[&(char, Point)], bounds: &Bounds, rule: &Rule) -> usize {
+    let antinodes: HashSet<Point> = frequency_antinodes(antennas, bounds, rule)
+        .into_values()
+        .flatten()
+        .collect();
+    antinodes.len()
+}
+
+// The antinodes each frequency creates on its own.
+fn frequency_antinodes(
+    antennas: &[&(char, Point)],
+    bounds: &Bounds,
+    rule: &Rule,
+) -> BTreeMap<char, HashSet<Point>> {
+    group_frequencies(antennas)
+        .into_iter()
+        .map(|(freq, positions)| {
+            let mut antinodes = HashSet::new();
+            // Check all pairs of antennas
+            for i in 0..positions.len() {
+                for j in (i + 1)..positions.len() {
+                    antinodes.extend(rule.antinodes(positions[i], positions[j], bounds));
+                }
+            }
+            (freq, antinodes)
+        })
+        .collect()
+}
 
-    // Group antennas by frequency
+fn group_frequencies<'a>(antennas: &[&'a (char, Point)]) -> BTreeMap<char, Vec<&'a Point>> {
+    let mut frequency_groups: BTreeMap<char, Vec<&Point>> = BTreeMap::new();
     for (freq, pos) in antennas {
         frequency_groups.entry(*freq).or_default().push(pos);
     }
+    frequency_groups
+}
 
-    // For each frequency group
-    for positions in frequency_groups.values() {
-        // Check all pairs of antennas
-        for i in 0..positions.len() {
-            for j in (i + 1)..positions.len() {
-                let a1 = positions[i];
-                let a2 = positions[j];
+// How much one frequency contributes to the antinode count.
+#[derive(Debug, PartialEq)]
+struct FrequencyReport {
+    frequency: char,
+    antennas: usize,
+    pairs: usize,
+    antinodes: usize,
+    // Antinodes no other frequency creates
+    unique: usize,
+}
 
-                let dx = a2.x - a1.x;
-                let dy = a2.y - a1.y;
+fn report(antennas: &[&(char, Point)], bounds: &Bounds, rule: &Rule) -> Vec<FrequencyReport> {
+    let groups = group_frequencies(antennas);
+    let antinodes = frequency_antinodes(antennas, bounds, rule);
+    let mut creators: HashMap<&Point, usize> = HashMap::new();
+    for c in antinodes.values().flatten() {
+        *creators.entry(c).or_default() += 1;
+    }
 
-                // Part 1 logic
-                let antinode1 = Coordinate {
-                    x: a1.x - dx,
-                    y: a1.y - dy,
-                };
-                let antinode2 = Coordinate {
-                    x: a2.x + dx,
-                    y: a2.y + dy,
-                };
+    groups
+        .iter()
+        .map(|(&frequency, positions)| {
+            let own = &antinodes[&frequency];
+            FrequencyReport {
+                frequency,
+                antennas: positions.len(),
+                pairs: positions.len() * positions.len().saturating_sub(1) / 2,
+                antinodes: own.len(),
+                unique: own.iter().filter(|c| creators[c] == 1).count(),
+            }
+        })
+        .collect()
+}
 
-                if bounds.contains(&antinode1) {
-                    antinodes_p1.insert(antinode1);
-                }
-                if bounds.contains(&antinode2) {
-                    antinodes_p1.insert(antinode2);
-                }
+fn report_table(rows: &[FrequencyReport]) -> String {
+    let mut out = String::from("Frequency  Antennas  Pairs  Antinodes  Unique\n");
+    for row in rows {
+        out.push_str(&format!(
+            "{:<9}  {:>8}  {:>5}  {:>9}  {:>6}\n",
+            row.frequency, row.antennas, row.pairs, row.antinodes, row.unique
+        ));
+    }
+    out
+}
 
-                // Part 2 logic
-                antinodes_p2.insert(*a1);
-                antinodes_p2.insert(*a2);
-
-                let mut k = 1;
-                loop {
-                    let antinode1 = Coordinate {
-                        x: a1.x - k * dx,
-                        y: a1.y - k * dy,
-                    };
-                    let antinode2 = Coordinate {
-                        x: a2.x + k * dx,
-                        y: a2.y + k * dy,
-                    };
-
-                    let mut added = false;
-                    if bounds.contains(&antinode1) {
-                        antinodes_p2.insert(antinode1);
-                        added = true;
-                    }
-                    if bounds.contains(&antinode2) {
-                        antinodes_p2.insert(antinode2);
-                        added = true;
-                    }
+fn report_json(rows: &[FrequencyReport]) -> String {
+    let rows: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{{\"frequency\":\"{}\",\"antennas\":{},\"pairs\":{},\"antinodes\":{},\"unique\":{}}}",
+                json_char(row.frequency),
+                row.antennas,
+                row.pairs,
+                row.antinodes,
+                row.unique
+            )
+        })
+        .collect();
+    format!("{{\"frequencies\":[{}]}}", rows.join(","))
+}
 
-                    if !added {
-                        break;
-                    }
-                    k += 1;
-                }
+// A frequency as it goes inside a JSON string. Any character but '.' can be
+// an antenna, quotes and backslashes included.
+fn json_char(c: char) -> String {
+    match c {
+        '"' => "\\\"".to_string(),
+        '\\' => "\\\\".to_string(),
+        c if c.is_control() => format!("\\u{:04x}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+// Draws the map the way the puzzle text does: antennas by their frequency, and
+// '#' at antinodes that aren't hidden under an antenna. Maps with more than two
+// dimensions are drawn as a stack of layers, the way they're read in.
+fn draw(antennas: &[&(char, Point)], bounds: &Bounds, rule: &Rule) -> String {
+    let antinodes: HashSet<Point> = frequency_antinodes(antennas, bounds, rule)
+        .into_values()
+        .flatten()
+        .collect();
+    // A one-dimensional map is a single row
+    let rows: Vec<Option<i32>> = match (bounds.min.0.get(1), bounds.max.0.get(1)) {
+        (Some(&min), Some(&max)) => (min..=max).map(Some).collect(),
+        _ => vec![None],
+    };
+    let mut layers = Vec::new();
+    // Counts through the layer axes like an odometer, the last one slowest
+    let mut layer = bounds.min.0.get(2..).unwrap_or_default().to_vec();
+    loop {
+        let mut out = String::new();
+        for y in &rows {
+            for x in bounds.min.0[0]..=bounds.max.0[0] {
+                let mut c = vec![x];
+                c.extend(y);
+                c.extend(&layer);
+                let c = Point(c);
+                out.push(match antennas.iter().find(|(_, pos)| *pos == c) {
+                    Some((freq, _)) => *freq,
+                    None if antinodes.contains(&c) => '#',
+                    None => '.',
+                });
             }
+            out.push('\n');
         }
-    }
+        layers.push(out);
 
-    (antinodes_p1.len(), antinodes_p2.len())
+        let axis = (0..layer.len()).find(|&i| layer[i] < bounds.max.0[i + 2]);
+        let Some(axis) = axis else {
+            break;
+        };
+        layer[axis] += 1;
+        layer[..axis].copy_from_slice(&bounds.min.0[2..axis + 2]);
+    }
+    layers.join("\n")
 }
 
 #[cfg(test)]
@@ -150,4 +489,191 @@ mod tests {
         let input_ref: Vec<_> = input.iter().collect();
         assert_eq!(solve(&input_ref, &bounds).1, 34);
     }
+
+    #[test]
+    fn test_rules() {
+        let (input, bounds) = parse_data(TESTDATA);
+        let input_ref: Vec<_> = input.iter().collect();
+        let line = |reduce, max_harmonics| Rule::Line {
+            reduce,
+            max_harmonics,
+        };
+        // Just the antennas, then part 1 plus the antennas
+        assert_eq!(
+            count_antinodes(&input_ref, &bounds, &line(false, Some(0))),
+            7
+        );
+        assert_eq!(
+            count_antinodes(&input_ref, &bounds, &line(false, Some(1))),
+            20
+        );
+
+        // Non-primitive offsets skip grid points unless reduced
+        let (input, bounds) = parse_data(
+            "a......
+.......
+.......
+...a...
+.......
+.......
+.......",
+        );
+        let input_ref: Vec<_> = input.iter().collect();
+        assert_eq!(count_antinodes(&input_ref, &bounds, &line(false, None)), 3);
+        assert_eq!(count_antinodes(&input_ref, &bounds, &line(true, None)), 7);
+        assert_eq!(count_antinodes(&input_ref, &bounds, &Rule::part1()), 1);
+
+        let thirds = ["1/3", "2/3"].iter().map(|r| Ratio::parse(r).unwrap());
+        let rule = Rule::Ratios(thirds.collect());
+        assert_eq!(count_antinodes(&input_ref, &bounds, &rule), 2);
+        assert_eq!(Ratio::parse("1/0"), None);
+
+        // A ratio applies from both antennas, whichever is listed first
+        let rule = Rule::Ratios(vec![Ratio::parse("3").unwrap()]);
+        let (input, bounds) = parse_data("....\n.a..\n..a.\n....");
+        let mut input_ref: Vec<_> = input.iter().collect();
+        let forwards = draw(&input_ref, &bounds, &rule);
+        input_ref.reverse();
+        assert_eq!(draw(&input_ref, &bounds, &rule), forwards);
+        assert_eq!(count_antinodes(&input_ref, &bounds, &rule), 0);
+        let rule = Rule::Ratios(vec![Ratio::parse("2").unwrap()]);
+        assert_eq!(count_antinodes(&input_ref, &bounds, &rule), 2);
+
+        // Harmonic limits too large to scale are unlimited
+        for h in [u32::MAX, i32::MAX as u32] {
+            assert_eq!(
+                count_antinodes(&input_ref, &bounds, &line(false, Some(h))),
+                4
+            );
+        }
+    }
+
+    #[test]
+    fn test_draw() {
+        let (input, bounds) = parse_data(TESTDATA);
+        let input_ref: Vec<_> = input.iter().collect();
+        assert_eq!(
+            draw(&input_ref, &bounds, &Rule::part1()),
+            "......#....#
+...#....0...
+....#0....#.
+..#....0....
+....0....#..
+.#....A.....
+...#........
+#......#....
+........A...
+.........A..
+..........#.
+..........#.
+"
+        );
+    }
+
+    #[test]
+    fn test_dimensions() {
+        // The sample as a coordinate listing gives the same answers
+        let (input, _) = parse_data(TESTDATA);
+        let listing: Vec<String> = input
+            .iter()
+            .map(|(freq, p)| format!("{}: {},{}", freq, p.0[0], p.0[1]))
+            .collect();
+        let (input, bounds) = parse_data(&format!("size: 12,12\n{}", listing.join("\n")));
+        let input_ref: Vec<_> = input.iter().collect();
+        assert_eq!(solve(&input_ref, &bounds), (14, 34));
+
+        let cube = "a..
+...
+...
+
+...
+.a.
+...
+
+...
+...
+...";
+        let (input, bounds) = parse_data(cube);
+        assert_eq!(bounds, Bounds::sized(&[3, 3, 3]));
+        assert_eq!(input[1], ('a', Point(vec![1, 1, 1])));
+        let input_ref: Vec<_> = input.iter().collect();
+        assert_eq!(solve(&input_ref, &bounds), (1, 3));
+        assert_eq!(
+            draw(&input_ref, &bounds, &Rule::part1()),
+            "a..\n...\n...\n\n...\n.a.\n...\n\n...\n...\n..#\n"
+        );
+
+        // Reducing finds the points between antennas in four dimensions too
+        let (input, bounds) = parse_data("size: 5,5,5,5\na: 0,0,0,0\na: 2,2,2,2\nb: 0,4,0,1");
+        let input_ref: Vec<_> = input.iter().collect();
+        assert_eq!(solve(&input_ref, &bounds), (1, 5));
+    }
+
+    #[test]
+    fn test_degenerate_grids() {
+        // Antennas sharing a point create no antinodes
+        let (input, bounds) = parse_data("size: 5,5\na: 1,1\na: 1,1");
+        let input_ref: Vec<_> = input.iter().collect();
+        assert_eq!(solve(&input_ref, &bounds), (0, 0));
+        let rule = Rule::Line {
+            reduce: false,
+            max_harmonics: None,
+        };
+        assert_eq!(count_antinodes(&input_ref, &bounds, &rule), 0);
+
+        let (input, bounds) = parse_data("size: 10\na: 2\na: 4");
+        let input_ref: Vec<_> = input.iter().collect();
+        assert_eq!(solve(&input_ref, &bounds), (2, 10));
+        assert_eq!(draw(&input_ref, &bounds, &Rule::part1()), "#.a.a.#...\n");
+
+        // Antinodes past the end of i32 are off the grid, not an overflow
+        let (input, bounds) = parse_data("size: 2000000000\na: 500000000\na: 1500000000");
+        let input_ref: Vec<_> = input.iter().collect();
+        assert_eq!(count_antinodes(&input_ref, &bounds, &Rule::part1()), 0);
+        assert_eq!(count_antinodes(&input_ref, &bounds, &rule), 2);
+    }
+
+    #[test]
+    fn test_report() {
+        let (input, bounds) = parse_data(TESTDATA);
+        let input_ref: Vec<_> = input.iter().collect();
+        let rows = report(&input_ref, &bounds, &Rule::part1());
+        assert_eq!(
+            rows,
+            [
+                FrequencyReport {
+                    frequency: '0',
+                    antennas: 4,
+                    pairs: 6,
+                    antinodes: 10,
+                    unique: 9,
+                },
+                FrequencyReport {
+                    frequency: 'A',
+                    antennas: 3,
+                    pairs: 3,
+                    antinodes: 5,
+                    unique: 4,
+                },
+            ]
+        );
+        assert_eq!(
+            report_json(&rows[1..]),
+            r#"{"frequencies":[{"frequency":"A","antennas":3,"pairs":3,"antinodes":5,"unique":4}]}"#
+        );
+        let rows: Vec<FrequencyReport> = ['"', '\\', '\t']
+            .into_iter()
+            .map(|frequency| FrequencyReport {
+                frequency,
+                antennas: 1,
+                pairs: 0,
+                antinodes: 0,
+                unique: 0,
+            })
+            .collect();
+        let json = report_json(&rows);
+        for escaped in [r#""\"""#, r#""\\""#, r#""\u0009""#] {
+            assert!(json.contains(&format!("\"frequency\":{},", escaped)));
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use aochelpers::get_daily_input;
+use std::{cmp::Reverse, collections::BinaryHeap};
 
 #[derive(Debug)]
 struct Span {
@@ -36,36 +37,47 @@ fn part1(data: &str) -> usize {
 }
 
 fn part2(data: &str) -> usize {
-    let (mut files, mut free) = parse_input(data, |f, i, p, s| {
+    let (mut files, free) = parse_input(data, |f, i, p, s| {
         f.push(Span {
             index: i,
             sector: p,
             size: s,
         })
     });
+    // One min-heap of free span start sectors per span size. Spans are at
+    // most 9 long, so the leftmost span a file fits in is the smallest top
+    // across the heaps for its size and up.
+    let mut by_size: [BinaryHeap<Reverse<usize>>; 10] = Default::default();
+    for ff in &free {
+        by_size[ff.size].push(Reverse(ff.sector));
+    }
     files.iter_mut().rev().for_each(|f| {
-        if let Some(ff) = free
-            .iter_mut()
-            .find(|ff| ff.sector < f.sector && ff.size >= f.size)
-        {
-            f.sector = ff.sector;
-            ff.sector += f.size;
-            ff.size -= f.size;
+        let leftmost = (f.size..by_size.len())
+            .filter_map(|size| by_size[size].peek().map(|&Reverse(sector)| (sector, size)))
+            .min();
+        if let Some((sector, size)) = leftmost.filter(|&(sector, _)| sector < f.sector) {
+            by_size[size].pop();
+            f.sector = sector;
+            // Whatever the file doesn't use stays free. The space it leaves
+            // behind is right of every file still to move, so it's never needed.
+            if size > f.size {
+                by_size[size - f.size].push(Reverse(sector + f.size));
+            }
         }
     });
     checksum(&files)
 }
 
-fn parse_input(data: &str, extend: impl Fn(&mut Vec<Span>, usize, usize, usize) -> ()) -> Disk {
+fn parse_input(data: &str, extend: impl Fn(&mut Vec<Span>, usize, usize, usize)) -> Disk {
     let mut sector = 0;
     let mut files = Vec::new();
     let mut free = Vec::new();
-    let mut r = vec![&mut files, &mut free];
+    let r = [&mut files, &mut free];
     for (i, c) in data.chars().enumerate() {
-        parse(c).map(|v| {
+        if let Some(v) = parse(c) {
             extend(r[i % 2], i / 2, sector, v as usize);
             sector += v as usize;
-        });
+        }
     }
     (files, free)
 }
@@ -74,7 +86,7 @@ fn parse(c: char) -> Option<u8> {
     c.to_digit(10).map(|v| v as u8)
 }
 
-fn checksum(files: &Vec<Span>) -> usize {
+fn checksum(files: &[Span]) -> usize {
     files
         .iter()
         .map(|f| {
@@ -111,9 +123,9 @@ mod tests {
         assert_eq!(solve(&input).0, 1928);
     }
 
-    // #[test]
-    // fn test_part2() {
-    //     let input = parse_data(TESTDATA);
-    //     assert_eq!(solve(&input).1, 0);
-    // }
+    #[test]
+    fn test_part2() {
+        let input = Input { data: TESTDATA };
+        assert_eq!(solve(&input).1, 2858);
+    }
 }